@@ -511,7 +511,7 @@ programCommand('verify')
     )) as Config;
 
     const lineCount = new anchor.BN(
-      config.data.slice(247, 247 + 4),
+      config.data.slice(CONFIG_ARRAY_START, CONFIG_ARRAY_START + 4),
       undefined,
       'le',
     );
@@ -782,6 +782,15 @@ programCommand('create_candy_machine')
         itemsAvailable: new anchor.BN(Object.keys(cacheContent.items).length),
         goLiveDate: null,
       },
+      {
+        burnToRedeemCreator: null,
+        creatorFund: null,
+        publicDate: null,
+        priorityCollection: null,
+        powDifficulty: 0,
+      },
+      null,
+      false,
       {
         accounts: {
           candyMachine,
@@ -832,6 +841,7 @@ programCommand('update_candy_machine')
       const tx = await anchorProgram.rpc.updateCandyMachine(
         lamports ? new anchor.BN(lamports) : null,
         secondsSinceEpoch ? new anchor.BN(secondsSinceEpoch) : null,
+        null,
         {
           accounts: {
             candyMachine,
@@ -39,6 +39,7 @@ export async function mint(
   const candyMachine: any = await anchorProgram.account.candyMachine.fetch(
     candyMachineAddress,
   );
+  const config: any = await anchorProgram.account.config.fetch(configAddress);
 
   const remainingAccounts = [];
   const signers = [mint, userKeyPair];
@@ -111,26 +112,41 @@ export async function mint(
   const masterEdition = await getMasterEdition(mint.publicKey);
 
   instructions.push(
-    await anchorProgram.instruction.mintNft({
-      accounts: {
-        config: configAddress,
-        candyMachine: candyMachineAddress,
-        payer: userKeyPair.publicKey,
-        //@ts-ignore
-        wallet: candyMachine.wallet,
-        mint: mint.publicKey,
-        metadata: metadataAddress,
-        masterEdition,
-        mintAuthority: userKeyPair.publicKey,
-        updateAuthority: userKeyPair.publicKey,
-        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        systemProgram: SystemProgram.programId,
-        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
-        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+    await anchorProgram.instruction.mintNft(
+      {
+        tipLamports: null,
+        powNonce: null,
+        mintToAta: false,
+        delegatedPayment: false,
+        memo: null,
+        paymentVaultBump: null,
+        holderDiscount: false,
+        authorization: null,
       },
-      remainingAccounts,
-    }),
+      {
+        accounts: {
+          config: configAddress,
+          candyMachine: candyMachineAddress,
+          payer: userKeyPair.publicKey,
+          //@ts-ignore
+          wallet: candyMachine.wallet,
+          mint: mint.publicKey,
+          metadata: metadataAddress,
+          masterEdition,
+          mintAuthority: userKeyPair.publicKey,
+          // retained authority stays with the candy machine authority
+          updateAuthority: config.data.retainAuthority
+            ? candyMachine.authority
+            : userKeyPair.publicKey,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+        remainingAccounts,
+      },
+    ),
   );
 
   if (tokenAccount) {
@@ -14,10 +14,12 @@ export async function withdraw(
 ): Promise<string> {
   const signers = [keypair];
   const instructions = [
-    anchorProgram.instruction.withdrawFunds({
+    // no amount sweeps the whole config and closes it, paying the authority
+    anchorProgram.instruction.withdrawFunds(null, null, {
       accounts: {
         config: configAddress,
         authority: keypair.publicKey,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
       },
     }),
   ];
@@ -110,8 +110,16 @@ export const createConfig = async function (
     txId: await anchorProgram.rpc.initializeConfig(
       {
         uuid,
+        namePrefix: null,
+        debugCompute: false,
+        externalUrl: null,
+        baseUri: null,
+        deferCreatorVerification: false,
+        withdrawDelay: null,
+        nameOffset: new anchor.BN(0),
         ...configData,
       },
+      null,
       {
         accounts: {
           config: configAccount.publicKey,
@@ -58,8 +58,24 @@ export const CONFIG_ARRAY_START =
   8 + //max supply
   1 + //is mutable
   1 + // retain authority
-  4; // max number of lines;
-export const CONFIG_LINE_SIZE = 4 + 32 + 4 + 200;
+  4 + // max number of lines
+  1 +
+  4 +
+  32 + // optional + u32 len + name prefix
+  1 + // debug compute
+  1 +
+  4 +
+  200 + // optional + u32 len + external url
+  1 +
+  4 +
+  168 + // optional + u32 len + base uri
+  1 + // defer creator verification
+  1 +
+  8 + // optional + withdraw delay
+  8 + // name offset
+  1; // frozen
+// name, uri, optional symbol and optional seller fee basis points
+export const CONFIG_LINE_SIZE = 4 + 32 + 4 + 200 + 1 + 4 + 10 + 1 + 2;
 
 export const CACHE_PATH = './.cache';
 
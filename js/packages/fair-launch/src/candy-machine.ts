@@ -22,6 +22,8 @@ interface CandyMachineState {
   itemsRedeemed: number;
   itemsRemaining: number;
   config: anchor.web3.PublicKey;
+  authority: anchor.web3.PublicKey;
+  retainAuthority: boolean;
   treasury: anchor.web3.PublicKey;
   tokenMint: anchor.web3.PublicKey;
   isSoldOut: boolean;
@@ -145,6 +147,7 @@ export const getCandyMachineState = async (
   const program = new anchor.Program(idl, CANDY_MACHINE_PROGRAM, provider);
 
   const state: any = await program.account.candyMachine.fetch(candyMachineId);
+  const config: any = await program.account.config.fetch(state.config);
   const itemsAvailable = state.data.itemsAvailable.toNumber();
   const itemsRedeemed = state.itemsRedeemed.toNumber();
   const itemsRemaining = itemsAvailable - itemsRedeemed;
@@ -162,6 +165,8 @@ export const getCandyMachineState = async (
       treasury: state.wallet,
       tokenMint: state.tokenMint,
       config: state.config,
+      authority: state.authority,
+      retainAuthority: config.data.retainAuthority,
       price: state.data.price,
     },
   };
@@ -281,26 +286,41 @@ export const mintOneToken = async (
   const masterEdition = await getMasterEdition(mint.publicKey);
 
   instructions.push(
-    await candyMachine.program.instruction.mintNft({
-      accounts: {
-        config: candyMachine.state.config,
-        candyMachine: candyMachineAddress,
-        payer,
-        wallet: candyMachine.state.treasury,
-        mint: mint.publicKey,
-        metadata: metadataAddress,
-        masterEdition,
-        mintAuthority: payer,
-        updateAuthority: payer,
-        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        systemProgram: SystemProgram.programId,
-        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
-        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+    await candyMachine.program.instruction.mintNft(
+      {
+        tipLamports: null,
+        powNonce: null,
+        mintToAta: false,
+        delegatedPayment: false,
+        memo: null,
+        paymentVaultBump: null,
+        holderDiscount: false,
+        authorization: null,
       },
-      remainingAccounts:
-        remainingAccounts.length > 0 ? remainingAccounts : undefined,
-    }),
+      {
+        accounts: {
+          config: candyMachine.state.config,
+          candyMachine: candyMachineAddress,
+          payer,
+          wallet: candyMachine.state.treasury,
+          mint: mint.publicKey,
+          metadata: metadataAddress,
+          masterEdition,
+          mintAuthority: payer,
+          // retained authority stays with the candy machine authority
+          updateAuthority: candyMachine.state.retainAuthority
+            ? candyMachine.state.authority
+            : payer,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+        remainingAccounts:
+          remainingAccounts.length > 0 ? remainingAccounts : undefined,
+      },
+    ),
   );
 
   if (tokenAccount) {
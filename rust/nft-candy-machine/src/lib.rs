@@ -1,19 +1,38 @@
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod utils;
+pub mod utils_test;
 
 use {
-    crate::utils::{assert_initialized, assert_owned_by, spl_token_transfer, TokenTransferParams},
+    crate::utils::{
+        active_priority_collection, assert_collection_holder, assert_index_assignee,
+        assert_initialized, assert_max_supply_within_guard, assert_metadata_lengths,
+        assert_mint_authorization, assert_mint_token_program, assert_minted_by, assert_owned_by,
+        assert_pass_balance, assert_payment_vault, assert_price_within_guard, assert_proof_of_work,
+        assert_redeemable_metadata, assert_token_wallet, consume_one, count_against_supply_cap,
+        count_against_throttle, count_wallet_mints, create_mint_escrow, expect_remaining, is_live,
+        log_compute_units, mint_creators, record_mint_authorization, resolve_price,
+        resolve_update_authority, spl_token_burn, spl_token_transfer, validate_candy_machine,
+        TokenBurnParams, TokenTransferParams,
+    },
     anchor_lang::{
-        prelude::*, solana_program::system_program, AnchorDeserialize, AnchorSerialize,
-        Discriminator, Key,
+        prelude::*,
+        solana_program::{program::invoke_signed, program_option::COption, system_program},
+        AnchorDeserialize, AnchorSerialize, Discriminator, Key,
     },
     anchor_spl::token::Token,
     arrayref::array_ref,
     metaplex_token_metadata::{
-        instruction::{create_master_edition, create_metadata_accounts, update_metadata_accounts},
+        instruction::{
+            create_master_edition, create_metadata_accounts, sign_metadata,
+            update_metadata_accounts,
+        },
         state::{
-            MAX_CREATOR_LEN, MAX_CREATOR_LIMIT, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
+            Data, MAX_CREATOR_LEN, MAX_CREATOR_LIMIT, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH,
+            MAX_URI_LENGTH,
         },
     },
+    spl_associated_token_account::get_associated_token_address,
     spl_token::state::Mint,
     std::cell::Ref,
 };
@@ -22,40 +41,248 @@ anchor_lang::declare_id!("cndyAnrLdpjq1Ssp1z8xxDsB8dxe7u4HL5Nxi2K5WXZ");
 const PREFIX: &str = "candy_machine";
 #[program]
 pub mod nft_candy_machine {
-    use anchor_lang::solana_program::{
-        program::{invoke, invoke_signed},
-        system_instruction,
-    };
+    use anchor_lang::solana_program::{program::invoke, system_instruction};
 
     use super::*;
 
-    pub fn mint_nft<'info>(ctx: Context<'_, '_, '_, 'info, MintNFT<'info>>) -> ProgramResult {
+    pub fn mint_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintNFT<'info>>,
+        args: MintArgs,
+    ) -> ProgramResult {
+        let MintArgs {
+            tip_lamports,
+            pow_nonce,
+            mint_to_ata,
+            delegated_payment,
+            memo,
+            payment_vault_bump,
+            holder_discount,
+            authorization,
+        } = args;
         let candy_machine = &mut ctx.accounts.candy_machine;
         let config = &ctx.accounts.config;
         let clock = &ctx.accounts.clock;
 
-        match candy_machine.data.go_live_date {
-            None => {
-                if *ctx.accounts.payer.key != candy_machine.authority {
-                    return Err(ErrorCode::CandyMachineNotLiveYet.into());
-                }
-            }
-            Some(val) => {
-                if clock.unix_timestamp < val {
-                    if *ctx.accounts.payer.key != candy_machine.authority {
-                        return Err(ErrorCode::CandyMachineNotLiveYet.into());
-                    }
-                }
+        validate_candy_machine(candy_machine, &config.key(), config)?;
+        assert_mint_token_program(
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        if candy_machine.data.go_live_date.is_some() && clock.unix_timestamp <= 0 {
+            msg!(
+                "Clock reads {}, treating the candy machine as not live",
+                clock.unix_timestamp
+            );
+        }
+        // Before go-live only the authority may mint, on the terms of its presale mode.
+        let presale_mode = candy_machine.authority_presale_mode;
+        let live = is_live(candy_machine.data.go_live_date, clock.unix_timestamp);
+        let authority_presale = !live && *ctx.accounts.payer.key == candy_machine.authority;
+        if !live && (!authority_presale || presale_mode == AuthorityPresaleMode::Disabled) {
+            if candy_machine.data.go_live_date.is_none() {
+                return Err(ErrorCode::CandyMachineNotConfigured.into());
             }
+            return Err(ErrorCode::CandyMachineNotLiveYet.into());
         }
 
         if candy_machine.items_redeemed >= candy_machine.data.items_available {
             return Err(ErrorCode::CandyMachineEmpty.into());
         }
+        let index = candy_machine.items_redeemed;
+        count_against_throttle(candy_machine, clock.slot)?;
+
+        if candy_machine.denylist.contains(ctx.accounts.payer.key) {
+            return Err(ErrorCode::AddressDenied.into());
+        }
+
+        let pow_difficulty = candy_machine.mint_options.pow_difficulty;
+        if pow_difficulty > 0 && *ctx.accounts.payer.key != candy_machine.authority {
+            assert_proof_of_work(
+                &candy_machine.key(),
+                ctx.accounts.payer.key,
+                pow_nonce.ok_or(ErrorCode::InvalidProofOfWork)?,
+                pow_difficulty,
+            )?;
+        }
+
+        let mut remaining_accounts_counter: usize = 0;
+        // Until the public date only holders of the priority collection may mint, proving it
+        // with their token account and its metadata.
+        if let Some(collection) = active_priority_collection(candy_machine, clock.unix_timestamp) {
+            if *ctx.accounts.payer.key != candy_machine.authority {
+                expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 2)?;
+                let holder_token_info = &ctx.remaining_accounts[remaining_accounts_counter];
+                let holder_metadata_info = &ctx.remaining_accounts[remaining_accounts_counter + 1];
+                remaining_accounts_counter += 2;
+
+                assert_collection_holder(
+                    holder_token_info,
+                    holder_metadata_info,
+                    ctx.accounts.payer.key,
+                    &collection,
+                    ErrorCode::PriorityHolderRequired,
+                )?;
+            }
+        }
+
+        // Once the authority assigned any line to a wallet, every mint shows the assignment
+        // PDA of the line it takes, so that line can only go to its wallet.
+        if candy_machine.assigned_indices > 0 {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+            let assignment_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            remaining_accounts_counter += 1;
+
+            assert_index_assignee(
+                assignment_info,
+                &candy_machine.key(),
+                index,
+                ctx.accounts.payer.key,
+            )?;
+        }
+
+        // the shared cap of all candy machines selling one collection
+        if let Some(supply_cap) = candy_machine.supply_cap {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+            let supply_cap_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            remaining_accounts_counter += 1;
+
+            count_against_supply_cap(supply_cap_info, &supply_cap)?;
+        }
+
+        // Allowlists kept off-chain: the authority signs the candy machine and payer keys with
+        // a nonce and an expiry, and the mint transaction carries that signature in an ed25519
+        // program instruction. The nonce is recorded in its own PDA so the signature is spent.
+        if candy_machine.signed_authorization && *ctx.accounts.payer.key != candy_machine.authority
+        {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 2)?;
+            let instructions_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            let record_info = &ctx.remaining_accounts[remaining_accounts_counter + 1];
+            remaining_accounts_counter += 2;
+            let authorization = authorization.ok_or(ErrorCode::InvalidMintAuthorization)?;
+
+            assert_mint_authorization(
+                instructions_info,
+                &candy_machine.authority,
+                &candy_machine.key(),
+                ctx.accounts.payer.key,
+                &authorization,
+                clock.unix_timestamp,
+            )?;
+            record_mint_authorization(
+                record_info,
+                &candy_machine.key(),
+                &ctx.accounts.payer.to_account_info(),
+                &authorization,
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.rent,
+            )?;
+        }
+
+        // Every payer counts its mints in its wallet mints PDA, see `initialize_wallet_mints`.
+        // With a pass mint the payer also shows its pass token account, and may mint as many
+        // items in total as it holds passes right now.
+        if candy_machine.rate_limit.is_some() || candy_machine.pass_mint.is_some() {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+            let wallet_mints_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            remaining_accounts_counter += 1;
+
+            let allocation = match candy_machine.pass_mint {
+                Some(pass_mint) => {
+                    expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+                    let pass_token_info = &ctx.remaining_accounts[remaining_accounts_counter];
+                    remaining_accounts_counter += 1;
+
+                    Some(assert_pass_balance(
+                        pass_token_info,
+                        ctx.accounts.payer.key,
+                        &pass_mint,
+                    )?)
+                }
+                None => None,
+            };
+
+            count_wallet_mints(
+                wallet_mints_info,
+                &candy_machine.key(),
+                ctx.accounts.payer.key,
+                candy_machine.rate_limit.as_ref(),
+                allocation,
+                clock.unix_timestamp,
+            )?;
+        }
+
+        let update_authority_info = ctx.accounts.update_authority.to_account_info();
+        let (new_update_authority, update_authority_must_sign) =
+            resolve_update_authority(config, candy_machine, &update_authority_info);
+        if *update_authority_info.key != new_update_authority {
+            return Err(ErrorCode::UpdateAuthorityMismatch.into());
+        }
+        if update_authority_must_sign && !update_authority_info.is_signer {
+            return Err(ErrorCode::UpdateAuthorityMustSign.into());
+        }
+
+        // Holders of the discount collection claim it with their token account and its
+        // metadata. Without a holder discount on the candy machine the flag is ignored.
+        let is_holder = match candy_machine.holder_discount {
+            Some(discount) if holder_discount => {
+                expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 2)?;
+                let holder_token_info = &ctx.remaining_accounts[remaining_accounts_counter];
+                let holder_metadata_info = &ctx.remaining_accounts[remaining_accounts_counter + 1];
+                remaining_accounts_counter += 2;
+
+                assert_collection_holder(
+                    holder_token_info,
+                    holder_metadata_info,
+                    ctx.accounts.payer.key,
+                    &discount.collection,
+                    ErrorCode::HolderDiscountRequired,
+                )?;
+                true
+            }
+            _ => false,
+        };
+
+        let price = if authority_presale && presale_mode == AuthorityPresaleMode::Free {
+            0
+        } else {
+            resolve_price(candy_machine, is_holder)?
+        };
+
+        let debug_compute = config.data.debug_compute;
+        log_compute_units(debug_compute, "before payment");
+
+        if let Some(burn_creator) = candy_machine.mint_options.burn_to_redeem_creator {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 4)?;
+            let burn_token_account_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            let burn_mint_info = &ctx.remaining_accounts[remaining_accounts_counter + 1];
+            let burn_metadata_info = &ctx.remaining_accounts[remaining_accounts_counter + 2];
+            let burn_authority_info = &ctx.remaining_accounts[remaining_accounts_counter + 3];
+            remaining_accounts_counter += 4;
+
+            assert_owned_by(burn_token_account_info, &spl_token::id())?;
+            let burn_token_account: spl_token::state::Account =
+                assert_initialized(burn_token_account_info)?;
+
+            if burn_token_account.mint != *burn_mint_info.key || burn_token_account.amount < 1 {
+                return Err(ErrorCode::InvalidBurnToken.into());
+            }
+
+            assert_redeemable_metadata(burn_metadata_info, burn_mint_info.key, &burn_creator)?;
 
-        if let Some(mint) = candy_machine.token_mint {
-            let token_account_info = &ctx.remaining_accounts[0];
-            let transfer_authority_info = &ctx.remaining_accounts[1];
+            spl_token_burn(TokenBurnParams {
+                mint: burn_mint_info.clone(),
+                source: burn_token_account_info.clone(),
+                amount: 1,
+                authority: burn_authority_info.clone(),
+                authority_signer_seeds: &[],
+                token_program: ctx.accounts.token_program.to_account_info(),
+            })?;
+        } else if let Some(mint) = candy_machine.token_mint {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 2)?;
+            let token_account_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            let transfer_authority_info = &ctx.remaining_accounts[remaining_accounts_counter + 1];
+            remaining_accounts_counter += 2;
             let token_account: spl_token::state::Account = assert_initialized(&token_account_info)?;
 
             assert_owned_by(&token_account_info, &spl_token::id())?;
@@ -64,153 +291,395 @@ pub mod nft_candy_machine {
                 return Err(ErrorCode::MintMismatch.into());
             }
 
-            if token_account.amount < candy_machine.data.price {
+            if token_account.amount < price {
                 return Err(ErrorCode::NotEnoughTokens.into());
             }
 
+            // only SOL proceeds can be held back for the withdraw delay
+            if config.data.withdraw_delay.is_some() {
+                return Err(ErrorCode::WithdrawDelayNeedsSolPrice.into());
+            }
+            // nor in a refund escrow, if the token mint was set after the refund window
+            if candy_machine.refund_window.is_some() {
+                return Err(ErrorCode::RefundWindowNeedsSolPrice.into());
+            }
+            // the wallet may have been closed since the token mint was set
+            let _wallet: spl_token::state::Account =
+                assert_initialized(&ctx.accounts.wallet).map_err(|_| ErrorCode::Uninitialized)?;
+
             spl_token_transfer(TokenTransferParams {
                 source: token_account_info.clone(),
                 destination: ctx.accounts.wallet.to_account_info(),
                 authority: transfer_authority_info.clone(),
                 authority_signer_seeds: &[],
                 token_program: ctx.accounts.token_program.to_account_info(),
-                amount: candy_machine.data.price,
+                amount: price,
             })?;
+            candy_machine.total_collected = candy_machine
+                .total_collected
+                .checked_add(price)
+                .ok_or(ErrorCode::NumericalOverflowError)?;
         } else {
-            if ctx.accounts.payer.lamports() < candy_machine.data.price {
+            // The price may be covered by another signer on the payer's behalf, e.g. a paymaster,
+            // or by the payer's vault, which the program signs for. Token payments need no such
+            // option since any approved transfer authority may pay.
+            let mut vault_bump = None;
+            let price_payer_info = if delegated_payment {
+                expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+                let fee_payer_info = &ctx.remaining_accounts[remaining_accounts_counter];
+                remaining_accounts_counter += 1;
+                if !fee_payer_info.is_signer {
+                    return Err(ErrorCode::FeePayerMustSign.into());
+                }
+                fee_payer_info.clone()
+            } else if let Some(bump) = payment_vault_bump {
+                expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+                let vault_info = &ctx.remaining_accounts[remaining_accounts_counter];
+                remaining_accounts_counter += 1;
+                assert_payment_vault(vault_info.key, ctx.accounts.payer.key, bump)?;
+                vault_bump = Some(bump);
+                vault_info.clone()
+            } else {
+                ctx.accounts.payer.to_account_info()
+            };
+
+            // With a refund window the price is held in an escrow of the mint until the buyer
+            // cancels the mint or the authority sweeps it, see cancel_mint.
+            let mut escrow = None;
+            if let Some(refund_window) = candy_machine.refund_window {
+                expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+                let escrow_info = &ctx.remaining_accounts[remaining_accounts_counter];
+                remaining_accounts_counter += 1;
+                let bump = create_mint_escrow(
+                    escrow_info,
+                    ctx.accounts.mint.key,
+                    &ctx.accounts.payer.to_account_info(),
+                    &ctx.accounts.system_program.to_account_info(),
+                    &ctx.accounts.rent,
+                )?;
+                let refundable_until = clock
+                    .unix_timestamp
+                    .checked_add(refund_window)
+                    .ok_or(ErrorCode::NumericalOverflowError)?;
+                let mint_escrow = MintEscrow {
+                    candy_machine: candy_machine.key(),
+                    buyer: *ctx.accounts.payer.key,
+                    mint: *ctx.accounts.mint.key,
+                    amount: 0,
+                    refundable_until,
+                    bump,
+                };
+                escrow = Some((escrow_info.clone(), mint_escrow));
+            }
+            // Otherwise a withdraw delay holds it in the candy machine, see release_held_proceeds.
+            let hold_proceeds = escrow.is_none() && config.data.withdraw_delay.is_some();
+            let price_destination_info = match &escrow {
+                Some((escrow_info, _)) => escrow_info.clone(),
+                None if hold_proceeds => candy_machine.to_account_info(),
+                None => ctx.accounts.wallet.to_account_info(),
+            };
+
+            // A payer that is also the wallet would only pay itself, so that transfer is
+            // skipped rather than rejected.
+            if price_payer_info.key != ctx.accounts.wallet.key {
+                if price_payer_info.lamports() < price {
+                    return Err(ErrorCode::NotEnoughSOL.into());
+                }
+
+                let vault_bump_seed = [vault_bump.unwrap_or_default()];
+                let vault_seeds = [
+                    PREFIX.as_bytes(),
+                    PAYMENT_VAULT.as_bytes(),
+                    ctx.accounts.payer.key.as_ref(),
+                    &vault_bump_seed,
+                ];
+                let signer_seeds: &[&[&[u8]]] = if vault_bump.is_some() {
+                    &[&vault_seeds]
+                } else {
+                    &[]
+                };
+                invoke_signed(
+                    &system_instruction::transfer(
+                        price_payer_info.key,
+                        price_destination_info.key,
+                        price,
+                    ),
+                    &[
+                        price_payer_info,
+                        price_destination_info,
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                    signer_seeds,
+                )?;
+                candy_machine.total_collected = candy_machine
+                    .total_collected
+                    .checked_add(price)
+                    .ok_or(ErrorCode::NumericalOverflowError)?;
+                if let Some((_, mint_escrow)) = escrow.as_mut() {
+                    mint_escrow.amount = price;
+                }
+                if hold_proceeds {
+                    candy_machine.held_proceeds = candy_machine
+                        .held_proceeds
+                        .checked_add(price)
+                        .ok_or(ErrorCode::NumericalOverflowError)?;
+                }
+            }
+
+            if let Some((escrow_info, mint_escrow)) = escrow {
+                mint_escrow.try_serialize(&mut &mut escrow_info.data.borrow_mut()[..])?;
+            }
+        }
+
+        if let Some(tip) = tip_lamports.filter(|tip| *tip > 0) {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+            let tip_account_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            remaining_accounts_counter += 1;
+
+            if candy_machine.mint_options.creator_fund != Some(*tip_account_info.key) {
+                return Err(ErrorCode::TipAccountMismatch.into());
+            }
+
+            if ctx.accounts.payer.lamports() < tip {
                 return Err(ErrorCode::NotEnoughSOL.into());
             }
 
             invoke(
-                &system_instruction::transfer(
-                    &ctx.accounts.payer.key,
-                    ctx.accounts.wallet.key,
-                    candy_machine.data.price,
-                ),
+                &system_instruction::transfer(ctx.accounts.payer.key, tip_account_info.key, tip),
                 &[
                     ctx.accounts.payer.to_account_info(),
-                    ctx.accounts.wallet.to_account_info(),
+                    tip_account_info.clone(),
                     ctx.accounts.system_program.to_account_info(),
                 ],
             )?;
         }
 
-        let config_line = get_config_line(
-            &config.to_account_info(),
-            candy_machine.items_redeemed as usize,
-        )?;
+        log_compute_units(debug_compute, "after payment");
 
-        candy_machine.items_redeemed = candy_machine
-            .items_redeemed
-            .checked_add(1)
-            .ok_or(ErrorCode::NumericalOverflowError)?;
+        // A launchpad listing the items right away takes them into its escrow, which the program
+        // mints into itself so the token can't land anywhere else.
+        if let Some(launchpad) = candy_machine.launchpad {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+            let escrow_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            remaining_accounts_counter += 1;
 
-        let config_key = config.key();
-        let authority_seeds = [
-            PREFIX.as_bytes(),
-            config_key.as_ref(),
-            candy_machine.data.uuid.as_bytes(),
-            &[candy_machine.bump],
-        ];
+            assert_owned_by(escrow_info, &spl_token::id())?;
+            let escrow: spl_token::state::Account = assert_initialized(escrow_info)?;
+            if escrow.mint != *ctx.accounts.mint.key || escrow.owner != launchpad {
+                return Err(ErrorCode::LaunchpadEscrowMismatch.into());
+            }
 
-        let mut creators: Vec<metaplex_token_metadata::state::Creator> =
-            vec![metaplex_token_metadata::state::Creator {
-                address: candy_machine.key(),
-                verified: true,
-                share: 0,
-            }];
-
-        for c in &config.data.creators {
-            creators.push(metaplex_token_metadata::state::Creator {
-                address: c.address,
-                verified: false,
-                share: c.share,
-            });
-        }
-
-        let metadata_infos = vec![
-            ctx.accounts.metadata.to_account_info(),
-            ctx.accounts.mint.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            ctx.accounts.payer.to_account_info(),
-            ctx.accounts.token_metadata_program.to_account_info(),
-            ctx.accounts.token_program.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.accounts.rent.to_account_info(),
-            candy_machine.to_account_info(),
-        ];
+            invoke(
+                &spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    ctx.accounts.mint.key,
+                    escrow_info.key,
+                    ctx.accounts.mint_authority.key,
+                    &[],
+                    1,
+                )?,
+                &[
+                    ctx.accounts.mint.to_account_info(),
+                    escrow_info.clone(),
+                    ctx.accounts.mint_authority.to_account_info(),
+                    ctx.accounts.token_program.to_account_info(),
+                ],
+            )?;
+        } else if mint_to_ata {
+            // Instead of the client creating its token account and minting the one token itself,
+            // the program can create the payer's associated token account if needed and mint
+            // into it.
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 2)?;
+            let ata_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            let ata_program_info = &ctx.remaining_accounts[remaining_accounts_counter + 1];
+            remaining_accounts_counter += 2;
+
+            if get_associated_token_address(ctx.accounts.payer.key, ctx.accounts.mint.key)
+                != *ata_info.key
+                || *ata_program_info.key != spl_associated_token_account::id()
+            {
+                return Err(ErrorCode::AssociatedTokenAccountMismatch.into());
+            }
 
-        let master_edition_infos = vec![
-            ctx.accounts.master_edition.to_account_info(),
-            ctx.accounts.mint.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            ctx.accounts.payer.to_account_info(),
-            ctx.accounts.metadata.to_account_info(),
-            ctx.accounts.token_metadata_program.to_account_info(),
-            ctx.accounts.token_program.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.accounts.rent.to_account_info(),
-            candy_machine.to_account_info(),
-        ];
+            if ata_info.data_is_empty() {
+                invoke(
+                    &spl_associated_token_account::create_associated_token_account(
+                        ctx.accounts.payer.key,
+                        ctx.accounts.payer.key,
+                        ctx.accounts.mint.key,
+                    ),
+                    &[
+                        ctx.accounts.payer.to_account_info(),
+                        ata_info.clone(),
+                        ctx.accounts.mint.to_account_info(),
+                        ctx.accounts.system_program.to_account_info(),
+                        ctx.accounts.token_program.to_account_info(),
+                        ctx.accounts.rent.to_account_info(),
+                        ata_program_info.clone(),
+                    ],
+                )?;
+            }
 
-        invoke_signed(
-            &create_metadata_accounts(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.metadata.key,
-                *ctx.accounts.mint.key,
-                *ctx.accounts.mint_authority.key,
-                *ctx.accounts.payer.key,
-                candy_machine.key(),
-                config_line.name,
-                config.data.symbol.clone(),
-                config_line.uri,
-                Some(creators),
-                config.data.seller_fee_basis_points,
-                true,
-                config.data.is_mutable,
-            ),
-            metadata_infos.as_slice(),
-            &[&authority_seeds],
-        )?;
+            invoke(
+                &spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    ctx.accounts.mint.key,
+                    ata_info.key,
+                    ctx.accounts.mint_authority.key,
+                    &[],
+                    1,
+                )?,
+                &[
+                    ctx.accounts.mint.to_account_info(),
+                    ata_info.clone(),
+                    ctx.accounts.mint_authority.to_account_info(),
+                    ctx.accounts.token_program.to_account_info(),
+                ],
+            )?;
+        }
 
-        invoke_signed(
-            &create_master_edition(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.master_edition.key,
-                *ctx.accounts.mint.key,
-                candy_machine.key(),
-                *ctx.accounts.mint_authority.key,
-                *ctx.accounts.metadata.key,
-                *ctx.accounts.payer.key,
-                Some(config.data.max_supply),
-            ),
-            master_edition_infos.as_slice(),
-            &[&authority_seeds],
+        // e.g. an order id integrators reconcile mints by
+        if let Some(memo) = memo {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+            let memo_program_info = &ctx.remaining_accounts[remaining_accounts_counter];
+            remaining_accounts_counter += 1;
+
+            if memo.len() > MAX_MEMO_LENGTH {
+                return Err(ErrorCode::MemoTooLong.into());
+            }
+            if *memo_program_info.key != spl_memo::id() {
+                return Err(ErrorCode::MemoProgramMismatch.into());
+            }
+
+            invoke(
+                &spl_memo::build_memo(memo.as_bytes(), &[]),
+                std::slice::from_ref(memo_program_info),
+            )?;
+        }
+
+        // For gasless mints a relayer submits the transaction and is paid back its fees out of
+        // the candy machine's own lamports, above what keeps it rent exempt and the proceeds
+        // it holds for the withdraw delay. set_relayer caps the reimbursement, since whether
+        // the relayer actually paid the fees can't be checked.
+        if let Some(relayer) = candy_machine.relayer {
+            expect_remaining(&ctx.remaining_accounts[remaining_accounts_counter..], 1)?;
+            let relayer_info = &ctx.remaining_accounts[remaining_accounts_counter];
+
+            if *relayer_info.key != relayer || !relayer_info.is_signer {
+                return Err(ErrorCode::RelayerMismatch.into());
+            }
+
+            let candy_machine_info = candy_machine.to_account_info();
+            let reimbursement = candy_machine.relayer_reimbursement;
+            let available = candy_machine_info
+                .lamports()
+                .saturating_sub(
+                    ctx.accounts
+                        .rent
+                        .minimum_balance(candy_machine_info.data_len()),
+                )
+                .saturating_sub(candy_machine.held_proceeds);
+            if available < reimbursement {
+                return Err(ErrorCode::RelayerReimbursementUnavailable.into());
+            }
+
+            **candy_machine_info.try_borrow_mut_lamports()? -= reimbursement;
+            **relayer_info.try_borrow_mut_lamports()? = relayer_info
+                .lamports()
+                .checked_add(reimbursement)
+                .ok_or(ErrorCode::NumericalOverflowError)?;
+        }
+
+        let config_line = get_config_line(&config.to_account_info(), index as usize)?;
+
+        create_metadata_and_master_edition(
+            candy_machine,
+            config,
+            index,
+            config_line,
+            new_update_authority,
+            MetadataAccounts {
+                metadata: ctx.accounts.metadata.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                mint_authority: ctx.accounts.mint_authority.to_account_info(),
+                payer: ctx.accounts.payer.to_account_info(),
+                master_edition: ctx.accounts.master_edition.to_account_info(),
+                token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
         )?;
 
-        let mut new_update_authority = Some(candy_machine.authority);
+        // Only count the item once its metadata and master edition exist, so items_redeemed
+        // always matches the NFTs actually minted.
+        consume_one(candy_machine)?;
+        // buyers already hold lines of this config, so they must stay as they were
+        ctx.accounts.config.frozen = true;
+
+        Ok(())
+    }
+
+    pub fn force_mint<'info>(ctx: Context<'_, '_, '_, 'info, ForceMint<'info>>) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+        let config = &ctx.accounts.config;
 
-        if !ctx.accounts.config.data.retain_authority {
-            new_update_authority = Some(ctx.accounts.update_authority.key());
+        if candy_machine.items_redeemed >= candy_machine.data.items_available {
+            return Err(ErrorCode::CandyMachineEmpty.into());
         }
+        let index = candy_machine.items_redeemed;
 
-        invoke_signed(
-            &update_metadata_accounts(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.metadata.key,
-                candy_machine.key(),
-                new_update_authority,
-                None,
-                Some(true),
-            ),
-            &[
-                ctx.accounts.token_metadata_program.to_account_info(),
-                ctx.accounts.metadata.to_account_info(),
-                candy_machine.to_account_info(),
-            ],
-            &[&authority_seeds],
+        let recipient_token_account: spl_token::state::Account =
+            assert_initialized(&ctx.accounts.recipient_token_account)?;
+        assert_owned_by(&ctx.accounts.recipient_token_account, &spl_token::id())?;
+
+        if recipient_token_account.mint != ctx.accounts.mint.key() {
+            return Err(ErrorCode::MintMismatch.into());
+        }
+
+        if recipient_token_account.owner != ctx.accounts.recipient.key()
+            || recipient_token_account.amount != 1
+        {
+            return Err(ErrorCode::RecipientMismatch.into());
+        }
+
+        let config_line = get_config_line(&config.to_account_info(), index as usize)?;
+
+        // the authority forcing the mint signs for the recipient
+        let (new_update_authority, _) = resolve_update_authority(
+            config,
+            candy_machine,
+            &ctx.accounts.recipient.to_account_info(),
+        );
+
+        create_metadata_and_master_edition(
+            candy_machine,
+            config,
+            index,
+            config_line,
+            new_update_authority,
+            MetadataAccounts {
+                metadata: ctx.accounts.metadata.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                mint_authority: ctx.accounts.mint_authority.to_account_info(),
+                payer: ctx.accounts.payer.to_account_info(),
+                master_edition: ctx.accounts.master_edition.to_account_info(),
+                token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
         )?;
 
+        // counted after the CPIs, as in mint_nft
+        consume_one(candy_machine)?;
+        // the shared cap counts forced mints too, its account being the only remaining account
+        if let Some(supply_cap) = candy_machine.supply_cap {
+            expect_remaining(ctx.remaining_accounts, 1)?;
+            count_against_supply_cap(&ctx.remaining_accounts[0], &supply_cap)?;
+        }
+        ctx.accounts.config.frozen = true;
+
         Ok(())
     }
 
@@ -218,29 +687,41 @@ pub mod nft_candy_machine {
         ctx: Context<UpdateCandyMachine>,
         price: Option<u64>,
         go_live_date: Option<i64>,
+        max_price_guard: Option<u64>,
     ) -> ProgramResult {
         let candy_machine = &mut ctx.accounts.candy_machine;
 
         if let Some(p) = price {
+            assert_price_within_guard(p, max_price_guard)?;
             candy_machine.data.price = p;
         }
 
         if let Some(go_l) = go_live_date {
+            // held proceeds are released relative to the go live date, see release_held_proceeds
+            if candy_machine.held_proceeds > 0 {
+                return Err(ErrorCode::GoLiveDateLocked.into());
+            }
             msg!("Go live date changed to {}", go_l);
             candy_machine.data.go_live_date = Some(go_l)
         }
         Ok(())
     }
 
-    pub fn initialize_config(ctx: Context<InitializeConfig>, data: ConfigData) -> ProgramResult {
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        data: ConfigData,
+        max_supply_guard: Option<u64>,
+    ) -> ProgramResult {
         let config_info = &mut ctx.accounts.config;
         if data.uuid.len() != 6 {
             return Err(ErrorCode::UuidMustBeExactly6Length.into());
         }
+        assert_max_supply_within_guard(data.max_supply, max_supply_guard)?;
 
         let mut config = Config {
             data,
             authority: *ctx.accounts.authority.key,
+            frozen: false,
         };
 
         let mut array_of_zeroes = vec![];
@@ -256,6 +737,31 @@ pub mod nft_candy_machine {
             return Err(ErrorCode::TooManyCreators.into());
         }
 
+        if let Some(external_url) = &config.data.external_url {
+            if external_url.len() > MAX_URI_LENGTH {
+                return Err(ErrorCode::ExternalUrlTooLong.into());
+            }
+        }
+
+        if let Some(base_uri) = &config.data.base_uri {
+            if base_uri.len() > MAX_BASE_URI_LENGTH {
+                return Err(ErrorCode::BaseUriTooLong.into());
+            }
+        }
+
+        if let Some(prefix) = &config.data.name_prefix {
+            // the longest generated name is the one for the last line
+            let last_number = config
+                .data
+                .name_offset
+                .checked_add(config.data.max_number_of_lines as u64)
+                .ok_or(ErrorCode::NumericalOverflowError)?;
+            let longest_name = format!("{} #{}", prefix, last_number);
+            if longest_name.len() > MAX_NAME_LENGTH {
+                return Err(ErrorCode::NamePrefixTooLong.into());
+            }
+        }
+
         let mut new_data = Config::discriminator().try_to_vec().unwrap();
         new_data.append(&mut config.try_to_vec().unwrap());
         let mut data = config_info.data.borrow_mut();
@@ -264,8 +770,9 @@ pub mod nft_candy_machine {
             data[i] = new_data[i];
         }
 
-        let vec_start =
-            CONFIG_ARRAY_START + 4 + (config.data.max_number_of_lines as usize) * CONFIG_LINE_SIZE;
+        let vec_start = CONFIG_ARRAY_START
+            + 4
+            + (config.data.max_number_of_lines as usize) * config_line_size(&config.data);
         let as_bytes = (config
             .data
             .max_number_of_lines
@@ -284,49 +791,97 @@ pub mod nft_candy_machine {
         index: u32,
         config_lines: Vec<ConfigLine>,
     ) -> ProgramResult {
+        // nothing to write, leave the lines, bitmask and count untouched
+        if config_lines.is_empty() {
+            return Ok(());
+        }
+        // Larger batches may run out of compute midway, rejecting them up front keeps the
+        // failure predictable.
+        if config_lines.len() > MAX_CONFIG_LINES_BATCH {
+            return Err(ErrorCode::BatchTooLarge.into());
+        }
+
         let config = &mut ctx.accounts.config;
+        if config.frozen {
+            return Err(ErrorCode::ConfigFrozen.into());
+        }
         let account = config.to_account_info();
         let current_count = get_config_count(&account.data.borrow())?;
         let mut data = account.data.borrow_mut();
 
-        let mut fixed_config_lines = vec![];
+        let line_size = config_line_size(&config.data);
+        let uri_length = config_uri_length(&config.data);
+        let mut serialized: Vec<u8> = vec![];
 
-        if index > config.data.max_number_of_lines - 1 {
+        if index as usize + config_lines.len() > config.data.max_number_of_lines as usize {
             return Err(ErrorCode::IndexGreaterThanLength.into());
         }
 
         for line in &config_lines {
+            if line.uri.len() > uri_length {
+                return Err(ErrorCode::UriTooLong.into());
+            }
+            let mut array_of_zeroes = vec![];
+            while array_of_zeroes.len() < uri_length - line.uri.len() {
+                array_of_zeroes.push(0u8);
+            }
+            let uri = line.uri.clone() + std::str::from_utf8(&array_of_zeroes).unwrap();
+
+            // compact configs generate names from the prefix, so only the uri is stored
+            if config.data.name_prefix.is_some() {
+                serialized.append(&mut uri.try_to_vec()?);
+                continue;
+            }
+
+            if line.name.len() > MAX_NAME_LENGTH {
+                return Err(ErrorCode::NameTooLong.into());
+            }
             let mut array_of_zeroes = vec![];
             while array_of_zeroes.len() < MAX_NAME_LENGTH - line.name.len() {
                 array_of_zeroes.push(0u8);
             }
             let name = line.name.clone() + std::str::from_utf8(&array_of_zeroes).unwrap();
 
+            // the symbol slot is always stored as Some so every line has the same size,
+            // an all zero symbol is read back as None
+            let line_symbol = line.symbol.clone().unwrap_or_default();
+            if line_symbol.len() > MAX_SYMBOL_LENGTH {
+                return Err(ErrorCode::SymbolTooLong.into());
+            }
             let mut array_of_zeroes = vec![];
-            while array_of_zeroes.len() < MAX_URI_LENGTH - line.uri.len() {
+            while array_of_zeroes.len() < MAX_SYMBOL_LENGTH - line_symbol.len() {
                 array_of_zeroes.push(0u8);
             }
-            let uri = line.uri.clone() + std::str::from_utf8(&array_of_zeroes).unwrap();
-            fixed_config_lines.push(ConfigLine { name, uri })
-        }
+            let symbol = Some(line_symbol + std::str::from_utf8(&array_of_zeroes).unwrap());
+
+            if let Some(seller_fee_basis_points) = line.seller_fee_basis_points {
+                if seller_fee_basis_points > 10000 {
+                    return Err(ErrorCode::InvalidBasisPoints.into());
+                }
+            }
 
-        let as_vec = fixed_config_lines.try_to_vec()?;
-        // remove unneeded u32 because we're just gonna edit the u32 at the front
-        let serialized: &[u8] = &as_vec.as_slice()[4..];
+            let mut line_data = ConfigLine {
+                name,
+                uri,
+                symbol,
+                seller_fee_basis_points: line.seller_fee_basis_points,
+            }
+            .try_to_vec()?;
+            // a missing seller fee is shorter, pad it so every line has the same size
+            line_data.resize(line_size, 0);
+            serialized.append(&mut line_data);
+        }
 
-        let position = CONFIG_ARRAY_START + 4 + (index as usize) * CONFIG_LINE_SIZE;
+        let position = CONFIG_ARRAY_START + 4 + (index as usize) * line_size;
 
-        let array_slice: &mut [u8] =
-            &mut data[position..position + fixed_config_lines.len() * CONFIG_LINE_SIZE];
-        array_slice.copy_from_slice(serialized);
+        let array_slice: &mut [u8] = &mut data[position..position + config_lines.len() * line_size];
+        array_slice.copy_from_slice(&serialized);
 
-        let bit_mask_vec_start = CONFIG_ARRAY_START
-            + 4
-            + (config.data.max_number_of_lines as usize) * CONFIG_LINE_SIZE
-            + 4;
+        let bit_mask_vec_start =
+            CONFIG_ARRAY_START + 4 + (config.data.max_number_of_lines as usize) * line_size + 4;
 
         let mut new_count = current_count;
-        for i in 0..fixed_config_lines.len() {
+        for i in 0..config_lines.len() {
             let position = (index as usize)
                 .checked_add(i)
                 .ok_or(ErrorCode::NumericalOverflowError)?;
@@ -364,6 +919,34 @@ pub mod nft_candy_machine {
         data[CONFIG_ARRAY_START..CONFIG_ARRAY_START + 4]
             .copy_from_slice(&(new_count as u32).to_le_bytes());
 
+        emit!(ConfigLinesAdded {
+            config: config.key(),
+            index,
+            count: config_lines.len() as u32,
+            new_total: new_count as u32,
+        });
+
+        Ok(())
+    }
+
+    pub fn verify_uploaded(ctx: Context<VerifyUploaded>, expected: u32) -> ProgramResult {
+        verify_config_upload(&ctx.accounts.config.to_account_info(), expected)
+    }
+
+    /// Recovers a config whose stored count disagrees with its lines, e.g. after a failed
+    /// upload, by resetting the count to the number of lines the bitmask marks as uploaded.
+    pub fn recompute_config_count(ctx: Context<RecomputeConfigCount>) -> ProgramResult {
+        let config = &ctx.accounts.config;
+        if config.frozen {
+            return Err(ErrorCode::ConfigFrozen.into());
+        }
+        let account = config.to_account_info();
+        let mut data = account.data.borrow_mut();
+
+        let count = count_uploaded_config_lines(&data, &config.data)?;
+        data[CONFIG_ARRAY_START..CONFIG_ARRAY_START + 4]
+            .copy_from_slice(&(count as u32).to_le_bytes());
+
         Ok(())
     }
 
@@ -371,29 +954,26 @@ pub mod nft_candy_machine {
         ctx: Context<InitializeCandyMachine>,
         bump: u8,
         data: CandyMachineData,
+        mint_options: MintOptions,
+        max_price_guard: Option<u64>,
+        require_full_config: bool,
     ) -> ProgramResult {
         let candy_machine = &mut ctx.accounts.candy_machine;
 
         if data.uuid.len() != 6 {
             return Err(ErrorCode::UuidMustBeExactly6Length.into());
         }
+        assert_price_within_guard(data.price, max_price_guard)?;
         candy_machine.data = data;
+        candy_machine.mint_options = mint_options;
         candy_machine.wallet = *ctx.accounts.wallet.key;
         candy_machine.authority = *ctx.accounts.authority.key;
         candy_machine.config = ctx.accounts.config.key();
         candy_machine.bump = bump;
         if ctx.remaining_accounts.len() > 0 {
             let token_mint_info = &ctx.remaining_accounts[0];
-            let _token_mint: Mint = assert_initialized(&token_mint_info)?;
-            let token_account: spl_token::state::Account =
-                assert_initialized(&ctx.accounts.wallet)?;
-
-            assert_owned_by(&token_mint_info, &spl_token::id())?;
-            assert_owned_by(&ctx.accounts.wallet, &spl_token::id())?;
-
-            if token_account.mint != *token_mint_info.key {
-                return Err(ErrorCode::MintMismatch.into());
-            }
+            candy_machine.token_decimals =
+                assert_token_wallet(&ctx.accounts.wallet, token_mint_info)?;
 
             candy_machine.token_mint = Some(*token_mint_info.key);
         }
@@ -403,6 +983,15 @@ pub mod nft_candy_machine {
         {
             return Err(ErrorCode::ConfigLineMismatch.into());
         }
+        // The count above also counts lines uploaded past items_available, so a config with
+        // gaps passes it and only fails once a mint reaches a gap.
+        if require_full_config {
+            assert_lines_uploaded(
+                &ctx.accounts.config.to_account_info().data.borrow(),
+                &ctx.accounts.config.data,
+                candy_machine.data.items_available as usize,
+            )?;
+        }
 
         let _config_line = match get_config_line(&ctx.accounts.config.to_account_info(), 0) {
             Ok(val) => val,
@@ -412,59 +1001,886 @@ pub mod nft_candy_machine {
         Ok(())
     }
 
-    pub fn update_authority(
+    pub fn set_relayer(
         ctx: Context<UpdateCandyMachine>,
-        new_authority: Option<Pubkey>,
+        relayer: Option<Pubkey>,
+        reimbursement: u64,
     ) -> ProgramResult {
+        if reimbursement > MAX_RELAYER_REIMBURSEMENT {
+            return Err(ErrorCode::RelayerReimbursementTooHigh.into());
+        }
         let candy_machine = &mut ctx.accounts.candy_machine;
+        candy_machine.relayer = relayer;
+        candy_machine.relayer_reimbursement = reimbursement;
 
-        if let Some(new_auth) = new_authority {
-            candy_machine.authority = new_auth;
+        Ok(())
+    }
+
+    pub fn initialize_supply_cap(
+        ctx: Context<InitializeSupplyCap>,
+        bump: u8,
+        _uuid: String,
+        max: u64,
+    ) -> ProgramResult {
+        let supply_cap = &mut ctx.accounts.supply_cap;
+        supply_cap.authority = *ctx.accounts.authority.key;
+        supply_cap.max = max;
+        supply_cap.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn set_rate_limit(
+        ctx: Context<UpdateCandyMachine>,
+        rate_limit: Option<RateLimit>,
+    ) -> ProgramResult {
+        ctx.accounts.candy_machine.rate_limit = rate_limit;
+
+        Ok(())
+    }
+
+    pub fn set_refund_window(
+        ctx: Context<UpdateCandyMachine>,
+        refund_window: Option<i64>,
+    ) -> ProgramResult {
+        // the escrow only ever holds lamports
+        if refund_window.is_some() && ctx.accounts.candy_machine.token_mint.is_some() {
+            return Err(ErrorCode::RefundWindowNeedsSolPrice.into());
         }
+        ctx.accounts.candy_machine.refund_window = refund_window;
 
         Ok(())
     }
 
-    pub fn withdraw_funds<'info>(ctx: Context<WithdrawFunds<'info>>) -> ProgramResult {
-        let authority = &ctx.accounts.authority;
-        let pay = &ctx.accounts.config.to_account_info();
-        let snapshot: u64 = pay.lamports();
+    /// Undoes a mint within its refund window, burning the item and returning the price held
+    /// in its escrow to the buyer together with the escrow's rent. Lines are minted in order,
+    /// so the line of a cancelled mint is gone for good and stays counted in items_redeemed,
+    /// while its price no longer counts as collected.
+    pub fn cancel_mint(ctx: Context<CancelMint>) -> ProgramResult {
+        if ctx.accounts.clock.unix_timestamp >= ctx.accounts.mint_escrow.refundable_until {
+            return Err(ErrorCode::RefundWindowClosed.into());
+        }
 
-        **pay.lamports.borrow_mut() = 0;
+        spl_token_burn(TokenBurnParams {
+            mint: ctx.accounts.mint.to_account_info(),
+            source: ctx.accounts.token_account.to_account_info(),
+            amount: 1,
+            authority: ctx.accounts.buyer.to_account_info(),
+            authority_signer_seeds: &[],
+            token_program: ctx.accounts.token_program.to_account_info(),
+        })?;
 
-        **authority.lamports.borrow_mut() = authority
-            .lamports()
-            .checked_add(snapshot)
+        let candy_machine = &mut ctx.accounts.candy_machine;
+        candy_machine.total_collected = candy_machine
+            .total_collected
+            .checked_sub(ctx.accounts.mint_escrow.amount)
             .ok_or(ErrorCode::NumericalOverflowError)?;
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(bump: u8, data: CandyMachineData)]
-pub struct InitializeCandyMachine<'info> {
-    #[account(init, seeds=[PREFIX.as_bytes(), config.key().as_ref(), data.uuid.as_bytes()], payer=payer, bump=bump, space=8+32+32+33+32+64+64+64+200)]
-    candy_machine: ProgramAccount<'info, CandyMachine>,
-    #[account(constraint= wallet.owner == &spl_token::id() || (wallet.data_is_empty() && wallet.lamports() > 0) )]
-    wallet: AccountInfo<'info>,
-    #[account(has_one=authority)]
-    config: ProgramAccount<'info, Config>,
-    #[account(signer, constraint= authority.data_is_empty() && authority.lamports() > 0)]
-    authority: AccountInfo<'info>,
-    #[account(mut, signer)]
-    payer: AccountInfo<'info>,
-    #[account(address = system_program::ID)]
-    system_program: AccountInfo<'info>,
-    rent: Sysvar<'info, Rent>,
-}
+    /// Pays the price held for a mint out to the wallet once its refund window closed. The
+    /// escrow's rent goes back to the buyer who funded it.
+    pub fn sweep_mint_escrow(ctx: Context<SweepMintEscrow>) -> ProgramResult {
+        let mint_escrow = &ctx.accounts.mint_escrow;
+        if ctx.accounts.clock.unix_timestamp < mint_escrow.refundable_until {
+            return Err(ErrorCode::RefundWindowOpen.into());
+        }
 
-#[derive(Accounts)]
-#[instruction(data: ConfigData)]
-pub struct InitializeConfig<'info> {
-    #[account(mut, constraint= config.to_account_info().owner == program_id && config.to_account_info().data_len() >= CONFIG_ARRAY_START+4+(data.max_number_of_lines as usize)*CONFIG_LINE_SIZE + 4 + (data.max_number_of_lines.checked_div(8).ok_or(ErrorCode::NumericalOverflowError)? as usize))]
-    config: AccountInfo<'info>,
-    #[account(constraint= authority.data_is_empty() && authority.lamports() > 0 )]
+        let escrow_info = mint_escrow.to_account_info();
+        let wallet = &ctx.accounts.wallet;
+        **escrow_info.lamports.borrow_mut() = escrow_info
+            .lamports()
+            .checked_sub(mint_escrow.amount)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+        **wallet.lamports.borrow_mut() = wallet
+            .lamports()
+            .checked_add(mint_escrow.amount)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+
+        Ok(())
+    }
+
+    pub fn set_authority_presale_mode(
+        ctx: Context<UpdateCandyMachine>,
+        authority_presale_mode: AuthorityPresaleMode,
+    ) -> ProgramResult {
+        ctx.accounts.candy_machine.authority_presale_mode = authority_presale_mode;
+
+        Ok(())
+    }
+
+    pub fn set_pass_mint(
+        ctx: Context<UpdateCandyMachine>,
+        pass_mint: Option<Pubkey>,
+    ) -> ProgramResult {
+        ctx.accounts.candy_machine.pass_mint = pass_mint;
+
+        Ok(())
+    }
+
+    pub fn initialize_wallet_mints(ctx: Context<InitializeWalletMints>, bump: u8) -> ProgramResult {
+        ctx.accounts.wallet_mints.bump = bump;
+
+        Ok(())
+    }
+
+    pub fn set_supply_cap(ctx: Context<SetSupplyCap>) -> ProgramResult {
+        ctx.accounts.candy_machine.supply_cap = Some(ctx.accounts.supply_cap.key());
+
+        Ok(())
+    }
+
+    pub fn set_launchpad(
+        ctx: Context<UpdateCandyMachine>,
+        launchpad: Option<Pubkey>,
+    ) -> ProgramResult {
+        ctx.accounts.candy_machine.launchpad = launchpad;
+
+        Ok(())
+    }
+
+    pub fn set_holder_discount(
+        ctx: Context<UpdateCandyMachine>,
+        holder_discount: Option<HolderDiscount>,
+    ) -> ProgramResult {
+        if let Some(discount) = holder_discount {
+            if discount.discount_basis_points > 10000 {
+                return Err(ErrorCode::DiscountTooHigh.into());
+            }
+        }
+        ctx.accounts.candy_machine.holder_discount = holder_discount;
+
+        Ok(())
+    }
+
+    pub fn set_mint_throttle(
+        ctx: Context<UpdateCandyMachine>,
+        max_per_slot: Option<u16>,
+    ) -> ProgramResult {
+        ctx.accounts.candy_machine.max_per_slot = max_per_slot;
+
+        Ok(())
+    }
+
+    pub fn set_signed_authorization(
+        ctx: Context<UpdateCandyMachine>,
+        signed_authorization: bool,
+    ) -> ProgramResult {
+        ctx.accounts.candy_machine.signed_authorization = signed_authorization;
+
+        Ok(())
+    }
+
+    pub fn set_token_mint(ctx: Context<SetTokenMint>) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+
+        if let Some(token_mint_info) = ctx.remaining_accounts.first() {
+            candy_machine.token_decimals =
+                assert_token_wallet(&ctx.accounts.wallet, token_mint_info)?;
+            candy_machine.token_mint = Some(*token_mint_info.key);
+        } else {
+            // SOL prices are transferred to the wallet, which a token account can't take
+            if *ctx.accounts.wallet.owner != system_program::ID {
+                return Err(ErrorCode::SolWalletMustBeSystemAccount.into());
+            }
+            candy_machine.token_mint = None;
+            candy_machine.token_decimals = 0;
+        }
+        candy_machine.wallet = *ctx.accounts.wallet.key;
+
+        Ok(())
+    }
+
+    pub fn create_treasury(ctx: Context<CreateTreasury>) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+        let treasury = &ctx.accounts.treasury;
+        let token_mint = &ctx.accounts.token_mint;
+
+        // the authority's associated token account for the mint, created if needed and then
+        // switched to as the wallet of the token payments
+        if get_associated_token_address(ctx.accounts.authority.key, token_mint.key) != *treasury.key
+        {
+            return Err(ErrorCode::AssociatedTokenAccountMismatch.into());
+        }
+
+        if treasury.data_is_empty() {
+            invoke(
+                &spl_associated_token_account::create_associated_token_account(
+                    ctx.accounts.payer.key,
+                    ctx.accounts.authority.key,
+                    token_mint.key,
+                ),
+                &[
+                    ctx.accounts.payer.to_account_info(),
+                    treasury.to_account_info(),
+                    ctx.accounts.authority.to_account_info(),
+                    token_mint.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.rent.to_account_info(),
+                    ctx.accounts.associated_token_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        candy_machine.token_decimals = assert_token_wallet(treasury, token_mint)?;
+        candy_machine.token_mint = Some(*token_mint.key);
+        candy_machine.wallet = *treasury.key;
+
+        Ok(())
+    }
+
+    pub fn mark_redeemed(ctx: Context<UpdateCandyMachine>, amount: u64) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+
+        // For items minted outside the candy machine. Lines are minted in order, so the next
+        // mint skips the lines these items are taken to have used.
+        let items_redeemed = candy_machine
+            .items_redeemed
+            .checked_add(amount)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+        if items_redeemed > candy_machine.data.items_available {
+            return Err(ErrorCode::RedeemedAboveAvailable.into());
+        }
+        candy_machine.items_redeemed = items_redeemed;
+
+        Ok(())
+    }
+
+    pub fn assign_index(
+        ctx: Context<AssignIndex>,
+        bump: u8,
+        index: u64,
+        wallet: Pubkey,
+    ) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+
+        if index >= candy_machine.data.items_available {
+            return Err(ErrorCode::IndexGreaterThanLength.into());
+        }
+
+        let index_assignment = &mut ctx.accounts.index_assignment;
+        index_assignment.wallet = wallet;
+        index_assignment.bump = bump;
+
+        candy_machine.assigned_indices = candy_machine
+            .assigned_indices
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+
+        Ok(())
+    }
+
+    /// Releases an index reserved by assign_index so any wallet can mint it again, e.g. when
+    /// its assignee never shows up and the in-order mints would otherwise stall on it.
+    pub fn unassign_index(ctx: Context<UnassignIndex>, _index: u64) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+
+        candy_machine.assigned_indices = candy_machine
+            .assigned_indices
+            .checked_sub(1)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+
+        Ok(())
+    }
+
+    pub fn update_authority(
+        ctx: Context<UpdateCandyMachine>,
+        new_authority: Option<Pubkey>,
+    ) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+
+        if let Some(new_auth) = new_authority {
+            candy_machine.authority = new_auth;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_config_authority(
+        ctx: Context<SetConfigAuthority>,
+        new_authority: Pubkey,
+    ) -> ProgramResult {
+        // The config is a plain keypair account, so its address doesn't depend on the
+        // authority and neither do the candy machines derived from it.
+        ctx.accounts.config.authority = new_authority;
+
+        Ok(())
+    }
+
+    pub fn add_to_denylist(ctx: Context<UpdateCandyMachine>, address: Pubkey) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+
+        if candy_machine.denylist.contains(&address) {
+            return Ok(());
+        }
+
+        if candy_machine.denylist.len() >= MAX_DENYLIST_LENGTH {
+            return Err(ErrorCode::DenylistFull.into());
+        }
+
+        candy_machine.denylist.push(address);
+
+        Ok(())
+    }
+
+    pub fn remove_from_denylist(
+        ctx: Context<UpdateCandyMachine>,
+        address: Pubkey,
+    ) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+
+        candy_machine.denylist.retain(|denied| *denied != address);
+
+        Ok(())
+    }
+
+    pub fn sign_metadata_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, SignMetadataBatch<'info>>,
+    ) -> ProgramResult {
+        let candy_machine = &ctx.accounts.candy_machine;
+        let config_key = candy_machine.config;
+        let authority_seeds = [
+            PREFIX.as_bytes(),
+            config_key.as_ref(),
+            candy_machine.data.uuid.as_bytes(),
+            &[candy_machine.bump],
+        ];
+
+        // every remaining account is the metadata of an NFT minted with
+        // defer_creator_verification
+        for metadata_info in ctx.remaining_accounts {
+            invoke_signed(
+                &sign_metadata(
+                    *ctx.accounts.token_metadata_program.key,
+                    *metadata_info.key,
+                    candy_machine.key(),
+                ),
+                &[
+                    metadata_info.clone(),
+                    candy_machine.to_account_info(),
+                    ctx.accounts.token_metadata_program.to_account_info(),
+                ],
+                &[&authority_seeds],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn lock_metadata_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, LockMetadataBatch<'info>>,
+    ) -> ProgramResult {
+        let candy_machine = &ctx.accounts.candy_machine;
+
+        // Emergency freeze of minted items: their update authority, held by the candy machine
+        // authority under retain_authority, goes to the default pubkey nobody can sign for.
+        // The token-metadata this builds against can't clear is_mutable after creation, so
+        // this is what makes them immutable.
+        for metadata_info in ctx.remaining_accounts {
+            assert_minted_by(metadata_info, &candy_machine.key())?;
+
+            invoke(
+                &update_metadata_accounts(
+                    *ctx.accounts.token_metadata_program.key,
+                    *metadata_info.key,
+                    *ctx.accounts.authority.key,
+                    Some(Pubkey::default()),
+                    None,
+                    None,
+                ),
+                &[
+                    ctx.accounts.token_metadata_program.to_account_info(),
+                    metadata_info.clone(),
+                    ctx.accounts.authority.to_account_info(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn reveal(ctx: Context<Reveal>, index: u32) -> ProgramResult {
+        let candy_machine = &ctx.accounts.candy_machine;
+        let config = &ctx.accounts.config;
+
+        // only then is the candy machine authority still the update authority of what it minted
+        if !config.data.retain_authority {
+            return Err(ErrorCode::RevealRequiresRetainAuthority.into());
+        }
+
+        let metadata_info = ctx.accounts.metadata.to_account_info();
+        let metadata = assert_minted_by(&metadata_info, &candy_machine.key())?;
+
+        let config_line = get_config_line(&config.to_account_info(), index as usize)?;
+
+        invoke(
+            &update_metadata_accounts(
+                *ctx.accounts.token_metadata_program.key,
+                *metadata_info.key,
+                *ctx.accounts.authority.key,
+                None,
+                Some(Data {
+                    name: config_line.name,
+                    symbol: config_line.symbol.unwrap_or(metadata.data.symbol),
+                    uri: config_line.uri,
+                    seller_fee_basis_points: config_line
+                        .seller_fee_basis_points
+                        .unwrap_or(metadata.data.seller_fee_basis_points),
+                    creators: metadata.data.creators,
+                }),
+                None,
+            ),
+            &[
+                ctx.accounts.token_metadata_program.to_account_info(),
+                metadata_info.clone(),
+                ctx.accounts.authority.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Permissionless, so dashboards can read the sale's progress from the event log.
+    pub fn emit_stats(ctx: Context<EmitStats>) -> ProgramResult {
+        let candy_machine = &ctx.accounts.candy_machine;
+        emit!(StatsEvent {
+            candy_machine: candy_machine.key(),
+            items_redeemed: candy_machine.items_redeemed,
+            items_available: candy_machine.data.items_available,
+            total_collected: candy_machine.total_collected,
+            timestamp: ctx.accounts.clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn refund<'info>(
+        ctx: Context<'_, '_, '_, 'info, Refund<'info>>,
+        amount: u64,
+    ) -> ProgramResult {
+        let candy_machine = &ctx.accounts.candy_machine;
+
+        if candy_machine.token_mint.is_some() {
+            // the wallet is a token account, its owner signs for the transfer back
+            expect_remaining(ctx.remaining_accounts, 2)?;
+            let buyer_token_account_info = &ctx.remaining_accounts[0];
+            let wallet_owner_info = &ctx.remaining_accounts[1];
+
+            assert_owned_by(buyer_token_account_info, &spl_token::id())?;
+            let buyer_token_account: spl_token::state::Account =
+                assert_initialized(buyer_token_account_info)?;
+            if buyer_token_account.owner != ctx.accounts.buyer.key() {
+                return Err(ErrorCode::RefundAccountMismatch.into());
+            }
+
+            spl_token_transfer(TokenTransferParams {
+                source: ctx.accounts.wallet.to_account_info(),
+                destination: buyer_token_account_info.clone(),
+                authority: wallet_owner_info.clone(),
+                authority_signer_seeds: &[],
+                token_program: ctx.accounts.token_program.to_account_info(),
+                amount,
+            })?;
+        } else {
+            invoke(
+                &system_instruction::transfer(
+                    ctx.accounts.wallet.key,
+                    ctx.accounts.buyer.key,
+                    amount,
+                ),
+                &[
+                    ctx.accounts.wallet.to_account_info(),
+                    ctx.accounts.buyer.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        emit!(RefundEvent {
+            candy_machine: candy_machine.key(),
+            buyer: ctx.accounts.buyer.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_funds<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawFunds<'info>>,
+        amount: Option<u64>,
+        rent_recipient: Option<Pubkey>,
+    ) -> ProgramResult {
+        // The funds may be routed to a treasury passed as the first remaining account, which
+        // must be a plain system account so they can't land in a program's state.
+        let recipient = match rent_recipient {
+            Some(rent_recipient) => {
+                expect_remaining(ctx.remaining_accounts, 1)?;
+                let recipient_info = &ctx.remaining_accounts[0];
+                if *recipient_info.key != rent_recipient
+                    || *recipient_info.owner != system_program::ID
+                {
+                    return Err(ErrorCode::InvalidRentRecipient.into());
+                }
+                recipient_info
+            }
+            None => &ctx.accounts.authority,
+        };
+        let pay = &ctx.accounts.config.to_account_info();
+        let snapshot: u64 = pay.lamports();
+
+        // A partial withdrawal leaves the config backing its candy machines, so it must stay
+        // rent exempt. Without an amount everything is swept and the config is closed.
+        let amount = match amount {
+            Some(amount) => {
+                let remaining = snapshot
+                    .checked_sub(amount)
+                    .ok_or(ErrorCode::WithdrawBelowRentExempt)?;
+                if !ctx.accounts.rent.is_exempt(remaining, pay.data_len()) {
+                    return Err(ErrorCode::WithdrawBelowRentExempt.into());
+                }
+                amount
+            }
+            None => snapshot,
+        };
+
+        **pay.lamports.borrow_mut() = snapshot - amount;
+
+        **recipient.lamports.borrow_mut() = recipient
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+
+        Ok(())
+    }
+
+    /// Pays the SOL prices a candy machine held for the withdraw delay out to its wallet, once
+    /// the delay after its go live date elapsed.
+    pub fn release_held_proceeds(ctx: Context<ReleaseHeldProceeds>) -> ProgramResult {
+        let candy_machine = &mut ctx.accounts.candy_machine;
+        let withdraw_delay = ctx.accounts.config.data.withdraw_delay.unwrap_or_default();
+        // without a go live date the sale hasn't started, so neither has the delay
+        let releasable_at = candy_machine
+            .data
+            .go_live_date
+            .ok_or(ErrorCode::WithdrawDelayNotElapsed)?
+            .checked_add(withdraw_delay)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+        if ctx.accounts.clock.unix_timestamp < releasable_at {
+            return Err(ErrorCode::WithdrawDelayNotElapsed.into());
+        }
+
+        let held_proceeds = candy_machine.held_proceeds;
+        let candy_machine_info = candy_machine.to_account_info();
+        let wallet = &ctx.accounts.wallet;
+        **candy_machine_info.lamports.borrow_mut() = candy_machine_info
+            .lamports()
+            .checked_sub(held_proceeds)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+        **wallet.lamports.borrow_mut() = wallet
+            .lamports()
+            .checked_add(held_proceeds)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+        candy_machine.held_proceeds = 0;
+
+        Ok(())
+    }
+
+    /// Moves `amount` out of the payer's payment vault to `destination`. The vault is only
+    /// ever debited with the payer's signature, either here or by mint_nft.
+    pub fn withdraw_payment_vault(
+        ctx: Context<WithdrawPaymentVault>,
+        bump: u8,
+        amount: u64,
+    ) -> ProgramResult {
+        let payer = &ctx.accounts.payer;
+        let vault = &ctx.accounts.vault;
+        assert_payment_vault(vault.key, payer.key, bump)?;
+
+        invoke_signed(
+            &system_instruction::transfer(vault.key, ctx.accounts.destination.key, amount),
+            &[
+                vault.to_account_info(),
+                ctx.accounts.destination.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&[
+                PREFIX.as_bytes(),
+                PAYMENT_VAULT.as_bytes(),
+                payer.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Accounts handed to token-metadata when turning a freshly minted token into an NFT.
+pub struct MetadataAccounts<'info> {
+    pub metadata: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub mint_authority: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub master_edition: AccountInfo<'info>,
+    pub token_metadata_program: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+}
+
+/// Creates the metadata and master edition for the config line at `index`, signing as the
+/// candy machine, then hands the update authority over to `new_update_authority` and emits a
+/// `MintEvent`.
+pub fn create_metadata_and_master_edition<'info>(
+    candy_machine: &Account<'info, CandyMachine>,
+    config: &Account<'info, Config>,
+    index: u64,
+    config_line: ConfigLine,
+    new_update_authority: Pubkey,
+    accounts: MetadataAccounts<'info>,
+) -> ProgramResult {
+    // token-metadata would reject these too, but deep inside its own checks
+    if !accounts.metadata.data_is_empty() || !accounts.master_edition.data_is_empty() {
+        return Err(ErrorCode::MetadataAlreadyExists.into());
+    }
+
+    // create_master_edition hands both the mint and the freeze authority over to the edition,
+    // signing with the mint authority, so a separate freeze authority can't survive the mint.
+    let mint: Mint = assert_initialized(&accounts.mint)?;
+    if mint.mint_authority != COption::Some(*accounts.mint_authority.key) {
+        return Err(ErrorCode::MintAuthorityMismatch.into());
+    }
+    if let COption::Some(freeze_authority) = mint.freeze_authority {
+        if freeze_authority != *accounts.mint_authority.key {
+            return Err(ErrorCode::FreezeAuthorityMismatch.into());
+        }
+    }
+
+    let config_key = config.key();
+    let authority_seeds = [
+        PREFIX.as_bytes(),
+        config_key.as_ref(),
+        candy_machine.data.uuid.as_bytes(),
+        &[candy_machine.bump],
+    ];
+
+    let creators = mint_creators(&candy_machine.key(), config)?;
+
+    let metadata_infos = vec![
+        accounts.metadata.clone(),
+        accounts.mint.clone(),
+        accounts.mint_authority.clone(),
+        accounts.payer.clone(),
+        accounts.token_metadata_program.clone(),
+        accounts.token_program.clone(),
+        accounts.system_program.clone(),
+        accounts.rent.clone(),
+        candy_machine.to_account_info(),
+    ];
+
+    let master_edition_infos = vec![
+        accounts.master_edition.clone(),
+        accounts.mint.clone(),
+        accounts.mint_authority.clone(),
+        accounts.payer.clone(),
+        accounts.metadata.clone(),
+        accounts.token_metadata_program.clone(),
+        accounts.token_program.clone(),
+        accounts.system_program.clone(),
+        accounts.rent.clone(),
+        candy_machine.to_account_info(),
+    ];
+
+    let debug_compute = config.data.debug_compute;
+    log_compute_units(debug_compute, "before create_metadata_accounts");
+
+    assert_metadata_lengths(&config_line)?;
+    let name = config_line.name.clone();
+    let uri = config_line.uri.clone();
+    invoke_signed(
+        &create_metadata_accounts(
+            *accounts.token_metadata_program.key,
+            *accounts.metadata.key,
+            *accounts.mint.key,
+            *accounts.mint_authority.key,
+            *accounts.payer.key,
+            candy_machine.key(),
+            config_line.name,
+            config_line
+                .symbol
+                .unwrap_or_else(|| config.data.symbol.clone()),
+            config_line.uri,
+            Some(creators),
+            config_line
+                .seller_fee_basis_points
+                .unwrap_or(config.data.seller_fee_basis_points),
+            true,
+            config.data.is_mutable,
+        ),
+        metadata_infos.as_slice(),
+        &[&authority_seeds],
+    )?;
+
+    log_compute_units(debug_compute, "after create_metadata_accounts");
+
+    invoke_signed(
+        &create_master_edition(
+            *accounts.token_metadata_program.key,
+            *accounts.master_edition.key,
+            *accounts.mint.key,
+            candy_machine.key(),
+            *accounts.mint_authority.key,
+            *accounts.metadata.key,
+            *accounts.payer.key,
+            Some(config.data.max_supply),
+        ),
+        master_edition_infos.as_slice(),
+        &[&authority_seeds],
+    )?;
+
+    log_compute_units(debug_compute, "after create_master_edition");
+
+    invoke_signed(
+        &update_metadata_accounts(
+            *accounts.token_metadata_program.key,
+            *accounts.metadata.key,
+            candy_machine.key(),
+            Some(new_update_authority),
+            None,
+            Some(true),
+        ),
+        &[
+            accounts.token_metadata_program.clone(),
+            accounts.metadata.clone(),
+            candy_machine.to_account_info(),
+        ],
+        &[&authority_seeds],
+    )?;
+
+    log_compute_units(debug_compute, "after update_metadata_accounts");
+
+    emit!(MintEvent {
+        candy_machine: candy_machine.key(),
+        mint: *accounts.mint.key,
+        external_url: config.data.external_url.clone(),
+        index,
+        name,
+        uri,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8, data: CandyMachineData)]
+pub struct InitializeCandyMachine<'info> {
+    #[account(init, seeds=[PREFIX.as_bytes(), config.key().as_ref(), data.uuid.as_bytes()], payer=payer, bump=bump, space=8+CandyMachine::LEN)]
+    candy_machine: ProgramAccount<'info, CandyMachine>,
+    #[account(constraint= wallet.owner == &spl_token::id() || (wallet.data_is_empty() && wallet.lamports() > 0) )]
+    wallet: AccountInfo<'info>,
+    // A config may back several candy machines of its authority, e.g. to relaunch the same
+    // lines with new pricing, each machine counting its own items_redeemed. This is the only
+    // point where a candy machine is tied to a config, so it is where both must answer to the
+    // same authority. Mints then trust the candy machine's has_one config, and either
+    // authority may be rotated on its own.
+    #[account(has_one=authority)]
+    config: ProgramAccount<'info, Config>,
+    #[account(signer, constraint= authority.data_is_empty() && authority.lamports() > 0)]
+    authority: AccountInfo<'info>,
+    #[account(mut, signer)]
+    payer: AccountInfo<'info>,
+    #[account(address = system_program::ID)]
+    system_program: AccountInfo<'info>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8, index: u64)]
+pub struct AssignIndex<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), candy_machine.config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: ProgramAccount<'info, CandyMachine>,
+    #[account(init, seeds=[PREFIX.as_bytes(), candy_machine.key().as_ref(), &index.to_le_bytes()], payer=payer, bump=bump, space=8+32+1)]
+    index_assignment: ProgramAccount<'info, IndexAssignment>,
+    #[account(signer)]
+    authority: AccountInfo<'info>,
+    #[account(mut, signer)]
+    payer: AccountInfo<'info>,
+    #[account(address = system_program::ID)]
+    system_program: AccountInfo<'info>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct UnassignIndex<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), candy_machine.config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: ProgramAccount<'info, CandyMachine>,
+    #[account(mut, seeds=[PREFIX.as_bytes(), candy_machine.key().as_ref(), &index.to_le_bytes()], bump=index_assignment.bump, close=authority)]
+    index_assignment: ProgramAccount<'info, IndexAssignment>,
+    #[account(mut, signer)]
+    authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8, uuid: String)]
+pub struct InitializeSupplyCap<'info> {
+    #[account(init, seeds=[PREFIX.as_bytes(), SUPPLY_CAP.as_bytes(), authority.key.as_ref(), uuid.as_bytes()], payer=payer, bump=bump, space=8+SupplyCap::LEN)]
+    supply_cap: ProgramAccount<'info, SupplyCap>,
+    #[account(signer)]
+    authority: AccountInfo<'info>,
+    #[account(mut, signer)]
+    payer: AccountInfo<'info>,
+    #[account(address = system_program::ID)]
+    system_program: AccountInfo<'info>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct InitializeWalletMints<'info> {
+    candy_machine: ProgramAccount<'info, CandyMachine>,
+    #[account(init, seeds=[PREFIX.as_bytes(), WALLET_MINTS.as_bytes(), candy_machine.key().as_ref(), payer.key.as_ref()], payer=payer, bump=bump, space=8+WalletMints::LEN)]
+    wallet_mints: ProgramAccount<'info, WalletMints>,
+    #[account(mut, signer)]
+    payer: AccountInfo<'info>,
+    #[account(address = system_program::ID)]
+    system_program: AccountInfo<'info>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetSupplyCap<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), candy_machine.config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: ProgramAccount<'info, CandyMachine>,
+    #[account(has_one = authority)]
+    supply_cap: ProgramAccount<'info, SupplyCap>,
+    #[account(signer)]
+    authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(data: ConfigData)]
+pub struct InitializeConfig<'info> {
+    #[account(mut, constraint= config.to_account_info().owner == program_id && config.to_account_info().data_len() >= CONFIG_ARRAY_START+4+(data.max_number_of_lines as usize)*config_line_size(&data) + 4 + (data.max_number_of_lines.checked_div(8).ok_or(ErrorCode::NumericalOverflowError)? as usize))]
+    config: AccountInfo<'info>,
+    #[account(constraint= authority.data_is_empty() && authority.lamports() > 0 )]
     authority: AccountInfo<'info>,
     #[account(mut, signer)]
     payer: AccountInfo<'info>,
@@ -478,15 +1894,110 @@ pub struct AddConfigLines<'info> {
     #[account(signer)]
     authority: AccountInfo<'info>,
 }
+#[derive(Accounts)]
+pub struct RecomputeConfigCount<'info> {
+    #[account(mut, has_one = authority)]
+    config: ProgramAccount<'info, Config>,
+    #[account(signer)]
+    authority: AccountInfo<'info>,
+}
+#[derive(Accounts)]
+pub struct VerifyUploaded<'info> {
+    config: Account<'info, Config>,
+}
+#[derive(Accounts)]
+pub struct CancelMint<'info> {
+    #[account(mut)]
+    candy_machine: Account<'info, CandyMachine>,
+    #[account(mut, has_one = candy_machine, has_one = buyer, has_one = mint, close = buyer)]
+    mint_escrow: Account<'info, MintEscrow>,
+    #[account(mut)]
+    buyer: Signer<'info>,
+    #[account(mut)]
+    mint: UncheckedAccount<'info>,
+    // spl-token checks it holds the mint and belongs to the buyer when burning
+    #[account(mut)]
+    token_account: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+    clock: Sysvar<'info, Clock>,
+}
+#[derive(Accounts)]
+pub struct SweepMintEscrow<'info> {
+    #[account(has_one = authority, has_one = wallet)]
+    candy_machine: Account<'info, CandyMachine>,
+    authority: Signer<'info>,
+    #[account(mut)]
+    wallet: UncheckedAccount<'info>,
+    #[account(mut, has_one = candy_machine, has_one = buyer, close = buyer)]
+    mint_escrow: Account<'info, MintEscrow>,
+    #[account(mut)]
+    buyer: UncheckedAccount<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+#[derive(Accounts)]
+pub struct EmitStats<'info> {
+    candy_machine: Account<'info, CandyMachine>,
+    clock: Sysvar<'info, Clock>,
+}
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(
+        has_one = authority,
+        has_one = wallet,
+        seeds = [PREFIX.as_bytes(), candy_machine.config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: Account<'info, CandyMachine>,
+    authority: Signer<'info>,
+    // Signs SOL refunds. In token mode its owner signs, passed in remaining_accounts.
+    #[account(mut)]
+    wallet: UncheckedAccount<'info>,
+    #[account(mut)]
+    buyer: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetConfigAuthority<'info> {
+    #[account(mut, has_one = authority)]
+    config: ProgramAccount<'info, Config>,
+    #[account(signer)]
+    authority: AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct WithdrawFunds<'info> {
     #[account(mut, has_one = authority)]
     config: Account<'info, Config>,
     #[account(signer, address = config.authority)]
     authority: AccountInfo<'info>,
+    rent: Sysvar<'info, Rent>,
+}
+#[derive(Accounts)]
+pub struct ReleaseHeldProceeds<'info> {
+    config: Account<'info, Config>,
+    #[account(mut, has_one = config, has_one = authority, has_one = wallet)]
+    candy_machine: Account<'info, CandyMachine>,
+    authority: Signer<'info>,
+    #[account(mut)]
+    wallet: UncheckedAccount<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+#[derive(Accounts)]
+pub struct WithdrawPaymentVault<'info> {
+    payer: Signer<'info>,
+    // the payment vault of the payer, checked in the handler
+    #[account(mut)]
+    vault: UncheckedAccount<'info>,
+    #[account(mut)]
+    destination: UncheckedAccount<'info>,
+    #[account(address = system_program::ID)]
+    system_program: UncheckedAccount<'info>,
 }
 #[derive(Accounts)]
 pub struct MintNFT<'info> {
+    #[account(mut)]
     config: Account<'info, Config>,
     #[account(
         mut,
@@ -507,7 +2018,9 @@ pub struct MintNFT<'info> {
     #[account(mut)]
     mint: UncheckedAccount<'info>,
     mint_authority: Signer<'info>,
-    update_authority: Signer<'info>,
+    // Must sign unless the config retains authority, in which case it is the candy machine
+    // authority. Checked in the handler.
+    update_authority: UncheckedAccount<'info>,
     #[account(mut)]
     master_edition: UncheckedAccount<'info>,
     #[account(address = metaplex_token_metadata::id())]
@@ -518,6 +2031,40 @@ pub struct MintNFT<'info> {
     clock: Sysvar<'info, Clock>,
 }
 
+#[derive(Accounts)]
+pub struct ForceMint<'info> {
+    #[account(mut)]
+    config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump,
+    )]
+    candy_machine: Account<'info, CandyMachine>,
+    authority: Signer<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    recipient: UncheckedAccount<'info>,
+    // Checked in the handler to hold the single freshly minted token for the recipient.
+    recipient_token_account: UncheckedAccount<'info>,
+    // With the following accounts we aren't using anchor macros because they are CPI'd
+    // through to token-metadata which will do all the validations we need on them.
+    #[account(mut)]
+    metadata: UncheckedAccount<'info>,
+    #[account(mut)]
+    mint: UncheckedAccount<'info>,
+    mint_authority: Signer<'info>,
+    #[account(mut)]
+    master_edition: UncheckedAccount<'info>,
+    #[account(address = metaplex_token_metadata::id())]
+    token_metadata_program: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateCandyMachine<'info> {
     #[account(
@@ -531,6 +2078,88 @@ pub struct UpdateCandyMachine<'info> {
     authority: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct SignMetadataBatch<'info> {
+    #[account(
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), candy_machine.config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: Account<'info, CandyMachine>,
+    authority: Signer<'info>,
+    #[account(address = metaplex_token_metadata::id())]
+    token_metadata_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LockMetadataBatch<'info> {
+    #[account(
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), candy_machine.config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: Account<'info, CandyMachine>,
+    authority: Signer<'info>,
+    #[account(address = metaplex_token_metadata::id())]
+    token_metadata_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Reveal<'info> {
+    config: Account<'info, Config>,
+    #[account(
+        has_one = config,
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: Account<'info, CandyMachine>,
+    authority: Signer<'info>,
+    #[account(mut)]
+    metadata: UncheckedAccount<'info>,
+    #[account(address = metaplex_token_metadata::id())]
+    token_metadata_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTokenMint<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), candy_machine.config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: ProgramAccount<'info, CandyMachine>,
+    #[account(signer)]
+    authority: AccountInfo<'info>,
+    #[account(constraint= wallet.owner == &spl_token::id() || (wallet.data_is_empty() && wallet.lamports() > 0) )]
+    wallet: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTreasury<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [PREFIX.as_bytes(), candy_machine.config.key().as_ref(), candy_machine.data.uuid.as_bytes()],
+        bump = candy_machine.bump
+    )]
+    candy_machine: Account<'info, CandyMachine>,
+    authority: Signer<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    // Checked in the handler to be the authority's associated token account for token_mint.
+    #[account(mut)]
+    treasury: UncheckedAccount<'info>,
+    #[account(owner = spl_token::id())]
+    token_mint: UncheckedAccount<'info>,
+    #[account(address = spl_associated_token_account::id())]
+    associated_token_program: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
 #[account]
 #[derive(Default)]
 pub struct CandyMachine {
@@ -541,8 +2170,175 @@ pub struct CandyMachine {
     pub data: CandyMachineData,
     pub items_redeemed: u64,
     pub bump: u8,
+    /// Payers that may not mint, at most MAX_DENYLIST_LENGTH of them
+    pub denylist: Vec<Pubkey>,
+    /// Number of config lines assign_index reserved for a wallet
+    pub assigned_indices: u32,
+    /// Owner of the escrow token account every item is minted into, for launchpads listing
+    /// them right away. Usually a PDA of the launchpad program.
+    pub launchpad: Option<Pubkey>,
+    /// Relayer that must sign every mint, paid back `relayer_reimbursement` lamports per mint
+    /// from the candy machine balance
+    pub relayer: Option<Pubkey>,
+    pub relayer_reimbursement: u64,
+    /// Supply cap shared with other candy machines, see `initialize_supply_cap`
+    pub supply_cap: Option<Pubkey>,
+    /// Only payers holding an ed25519 signature of the authority may mint, see
+    /// `assert_mint_authorization`
+    pub signed_authorization: bool,
+    /// Decimals of `token_mint`, so clients can turn a price in UI units into the raw amount
+    /// charged, see `ui_amount_to_raw`
+    pub token_decimals: u8,
+    /// Discount for holders of a partner collection, see `HolderDiscount`
+    pub holder_discount: Option<HolderDiscount>,
+    /// Caps how often each payer mints, see `RateLimit`
+    pub rate_limit: Option<RateLimit>,
+    /// Mints allowed per slot across all payers, to shield RPC nodes during a hot launch
+    pub max_per_slot: Option<u16>,
+    /// Slot of the last mint and how many mints it saw, for `max_per_slot`
+    pub last_mint_slot: u64,
+    pub mints_this_slot: u16,
+    /// Payers may mint as many items in total as they hold tokens of this mint, which are
+    /// checked but not burned
+    pub pass_mint: Option<Pubkey>,
+    /// Price paid into the wallet across all mints, in lamports or token_mint units. Refunds
+    /// and mints paid by burning are not reflected, cancelled mints are.
+    pub total_collected: u64,
+    /// What the authority may mint before go-live
+    pub authority_presale_mode: AuthorityPresaleMode,
+    /// Seconds after a mint during which the buyer may cancel it for a refund, the price
+    /// being held in a `MintEscrow` meanwhile. Only applies to SOL payments.
+    pub refund_window: Option<i64>,
+    /// SOL prices held back for the withdraw delay, which the candy machine keeps on top of
+    /// its rent until release_held_proceeds
+    pub held_proceeds: u64,
+    pub mint_options: MintOptions,
+}
+
+impl CandyMachine {
+    /// Serialized size of a candy machine with every option set and a full denylist, not
+    /// counting the 8 byte discriminator.
+    pub const LEN: usize = 32 + // authority
+        32 + // wallet
+        33 + // token mint
+        32 + // config
+        CandyMachineData::LEN +
+        8 + // items redeemed
+        1 + // bump
+        4 + MAX_DENYLIST_LENGTH * 32 + // denylist
+        4 + // assigned indices
+        33 + // launchpad
+        33 + // relayer
+        8 + // relayer reimbursement
+        33 + // supply cap
+        1 + // signed authorization
+        1 + // token decimals
+        35 + // holder discount
+        11 + // rate limit
+        3 + // max per slot
+        8 + // last mint slot
+        2 + // mints this slot
+        33 + // pass mint
+        8 + // total collected
+        1 + // authority presale mode
+        9 + // refund window
+        8 + // held proceeds
+        MintOptions::LEN;
+}
+
+const SUPPLY_CAP: &str = "supply_cap";
+/// Seed of the system owned vault a payer can fund to have mint_nft pay from it, derived
+/// with the payer key
+const PAYMENT_VAULT: &str = "payment_vault";
+
+/// Caps the mints of all candy machines pointing at it, for a collection sold through several.
+#[account]
+#[derive(Default)]
+pub struct SupplyCap {
+    pub authority: Pubkey,
+    pub max: u64,
+    /// Items minted so far across the candy machines
+    pub minted: u64,
+    pub bump: u8,
+}
+
+impl SupplyCap {
+    pub const LEN: usize = 32 + 8 + 8 + 1;
+}
+
+const WALLET_MINTS: &str = "wallet_mints";
+
+/// Mints of one payer from a candy machine, in the current rate limit window and in total.
+#[account]
+#[derive(Default)]
+pub struct WalletMints {
+    pub window_start: i64,
+    pub minted: u16,
+    pub bump: u8,
+    pub total_minted: u32,
+}
+
+impl WalletMints {
+    pub const LEN: usize = 8 + 2 + 1 + 4;
+}
+
+const MINT_ESCROW: &str = "mint_escrow";
+
+/// Price paid for one mint of a candy machine with a refund window, held until the buyer
+/// cancels the mint or the authority sweeps it. Program owned, at the PDA of
+/// [PREFIX, MINT_ESCROW, mint].
+#[account]
+#[derive(Default)]
+pub struct MintEscrow {
+    pub candy_machine: Pubkey,
+    pub buyer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub refundable_until: i64,
+    pub bump: u8,
+}
+
+impl MintEscrow {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 1;
+}
+
+const MINT_AUTHORIZATION: &str = "mint_authorization";
+
+/// Signed mint authorization whose nonce was used, see `utils::record_mint_authorization`.
+/// Program owned, at the PDA of [PREFIX, MINT_AUTHORIZATION, candy_machine, nonce].
+#[account]
+#[derive(Default)]
+pub struct UsedMintAuthorization {
+    pub candy_machine: Pubkey,
+    pub payer: Pubkey,
+    pub nonce: u64,
+    pub expires_at: i64,
+}
+
+impl UsedMintAuthorization {
+    pub const LEN: usize = 32 + 32 + 8 + 8;
+}
+
+/// Reserves the config line at an index for one wallet, see `assign_index`.
+#[account]
+#[derive(Default)]
+pub struct IndexAssignment {
+    pub wallet: Pubkey,
+    pub bump: u8,
 }
 
+pub const MAX_DENYLIST_LENGTH: usize = 8;
+
+pub const MAX_MEMO_LENGTH: usize = 128;
+
+/// Most lines a single add_config_lines call takes, the batch size uploaders already use
+pub const MAX_CONFIG_LINES_BATCH: usize = 10;
+
+/// Most lamports a relayer is paid back per mint, the fees of five signatures. The program
+/// can't tell which signer paid the fees of a transaction, so this bounds what the relayer
+/// gets for co-signing a mint someone else paid for.
+pub const MAX_RELAYER_REIMBURSEMENT: u64 = 5 * 5000;
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
 pub struct CandyMachineData {
     pub uuid: String,
@@ -551,6 +2347,115 @@ pub struct CandyMachineData {
     pub go_live_date: Option<i64>,
 }
 
+impl CandyMachineData {
+    pub const LEN: usize = 4 + 6 + // uuid, always 6 long
+        8 + // price
+        8 + // items available
+        9; // go live date
+}
+
+/// Optional ways of paying for and gating mints, set at initialization. Stored at the end of
+/// the candy machine rather than in `CandyMachineData` so the fields candy machines launched
+/// with keep their offsets.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct MintOptions {
+    /// When set, buyers pay by burning an NFT carrying this verified creator
+    /// (such as another candy machine) instead of paying the price
+    pub burn_to_redeem_creator: Option<Pubkey>,
+    /// Account receiving the optional SOL tips buyers add on top of the price
+    pub creator_fund: Option<Pubkey>,
+    /// When the public window opens, until then only priority collection holders can mint
+    pub public_date: Option<i64>,
+    /// Verified creator identifying the collection whose holders mint before the public date
+    pub priority_collection: Option<Pubkey>,
+    /// Leading zero bits buyers have to find in sha256(candy machine, payer, nonce), 0 to disable
+    pub pow_difficulty: u8,
+}
+
+impl MintOptions {
+    pub const LEN: usize = 33 + // burn to redeem creator
+        33 + // creator fund
+        9 + // public date
+        33 + // priority collection
+        1; // pow difficulty
+}
+
+/// Per-mint choices of the buyer, see `mint_nft`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct MintArgs {
+    /// SOL added on top of the price and sent to the creator fund
+    pub tip_lamports: Option<u64>,
+    /// Solution to the proof-of-work puzzle, required when `pow_difficulty` is set
+    pub pow_nonce: Option<u64>,
+    /// Create the payer's associated token account if needed and mint into it
+    pub mint_to_ata: bool,
+    /// Have another signer pay the price on the payer's behalf
+    pub delegated_payment: bool,
+    /// Note recorded with the SPL memo program alongside the mint
+    pub memo: Option<String>,
+    /// Pay the price from the payer's payment vault, which has this bump
+    pub payment_vault_bump: Option<u8>,
+    /// Claim the holder discount by proving ownership of an NFT from the discount collection
+    pub holder_discount: bool,
+    /// Terms of the authority's signature, required when `signed_authorization` is on
+    pub authorization: Option<MintAuthorization>,
+}
+
+/// Terms the authority signs along with the candy machine and payer keys to authorize a
+/// mint, see `utils::assert_mint_authorization`. Each nonce works once per candy machine.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct MintAuthorization {
+    pub nonce: u64,
+    /// Last unix timestamp the signature can be used at
+    pub expires_at: i64,
+}
+
+/// Cut of the price for buyers holding an NFT of `collection`, the verified creator
+/// identifying the collection like `priority_collection` does.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct HolderDiscount {
+    pub collection: Pubkey,
+    pub discount_basis_points: u16,
+}
+
+/// Lets each payer mint at most `max_mints` items per window of `window_seconds`, e.g. one
+/// free mint per day. A window starts with the first mint after the previous one ended.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RateLimit {
+    pub max_mints: u16,
+    pub window_seconds: i64,
+}
+
+/// Whether the authority mints before go-live paying the price, for free, or not at all.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum AuthorityPresaleMode {
+    #[default]
+    Charged,
+    Free,
+    Disabled,
+}
+
+/// Everything a front end needs to render the mint button, see `utils::mint_state`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct MintState {
+    pub is_live: bool,
+    pub is_sold_out: bool,
+    pub remaining: u64,
+    pub active_price: u64,
+}
+
+/// How the supply of a candy machine splits up, see `utils::supply_breakdown`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct SupplyBreakdown {
+    pub available: u64,
+    pub redeemed: u64,
+    pub reserved: u64,
+    pub public_remaining: u64,
+}
+
+/// Where the config lines start. Header fields are only ever appended, but each one still
+/// moves the lines, so configs written with a shorter header can't be read with this layout
+/// and have to be recreated. The CLI mirrors this in `helpers/constants.ts`.
 pub const CONFIG_ARRAY_START: usize = 32 + // authority
 4 + 6 + // uuid + u32 len
 4 + MAX_SYMBOL_LENGTH + // u32 len + symbol
@@ -559,13 +2464,24 @@ pub const CONFIG_ARRAY_START: usize = 32 + // authority
 8 + //max supply
 1 + // is mutable
 1 + // retain authority
-4; // max number of lines;
+4 + // max number of lines
+1 + 4 + MAX_NAME_LENGTH + // optional + u32 len + name prefix
+1 + // debug compute
+1 + 4 + MAX_URI_LENGTH + // optional + u32 len + external url
+1 + 4 + MAX_BASE_URI_LENGTH + // optional + u32 len + base uri
+1 + // defer creator verification
+1 + 8 + // optional + withdraw delay
+8 + // name offset
+1; // frozen
 
 #[account]
 #[derive(Default)]
 pub struct Config {
     pub authority: Pubkey,
     pub data: ConfigData,
+    /// Set by the first mint of any candy machine using this config, after which its lines
+    /// can't change anymore
+    pub frozen: bool,
     // there's a borsh vec u32 denoting how many actual lines of data there are currently (eventually equals max number of lines)
     // There is actually lines and lines of data after this but we explicitly never want them deserialized.
     // here there is a borsh vec u32 indicating number of bytes in bitmask array.
@@ -580,41 +2496,348 @@ pub struct ConfigData {
     /// Royalty basis points that goes to creators in secondary sales (0-10000)
     pub seller_fee_basis_points: u16,
     pub creators: Vec<Creator>,
+    /// Prints each master edition allows, passed as is to token-metadata. 0 keeps every item
+    /// unique while large values amount to unlimited prints.
     pub max_supply: u64,
     pub is_mutable: bool,
     pub retain_authority: bool,
     pub max_number_of_lines: u32,
+    /// When set, the config is stored in compact mode: lines only hold their uri
+    /// and names are generated as "{name_prefix} #{name_offset + index + 1}"
+    pub name_prefix: Option<String>,
+    /// Logs the remaining compute units around each step of a mint, for profiling
+    pub debug_compute: bool,
+    /// Collection level link token-metadata has no room for, emitted with every mint
+    pub external_url: Option<String>,
+    /// When set, lines only store a uri suffix of at most MAX_URI_SUFFIX_LENGTH and
+    /// the minted uri is "{base_uri}{suffix}"
+    pub base_uri: Option<String>,
+    /// Leaves the candy machine creator unverified in every mint, the authority verifies
+    /// minted NFTs later in batches with `sign_metadata_batch` instead
+    pub defer_creator_verification: bool,
+    /// Seconds after the go live date of each candy machine before `release_held_proceeds`
+    /// pays the SOL prices it held to its wallet, leaving buyers time to raise disputes. A
+    /// refund window escrows the prices per mint instead, and token prices are refused.
+    pub withdraw_delay: Option<i64>,
+    /// Added to the generated item numbers, so machines sharing a name prefix can number
+    /// their items contiguously instead of each starting at 1
+    pub name_offset: u64,
 }
 
 pub fn get_config_count(data: &Ref<&mut [u8]>) -> core::result::Result<usize, ProgramError> {
     return Ok(u32::from_le_bytes(*array_ref![data, CONFIG_ARRAY_START, 4]) as usize);
 }
 
+/// Counts the lines marked as uploaded in the bitmask following the lines, ignoring the
+/// stored count. Only the first `max_number_of_lines` bits stand for lines, the rest of the
+/// last byte is padding.
+pub fn count_uploaded_config_lines(
+    data: &[u8],
+    config_data: &ConfigData,
+) -> core::result::Result<usize, ProgramError> {
+    let max_number_of_lines = config_data.max_number_of_lines as usize;
+    let bit_mask_start =
+        CONFIG_ARRAY_START + 4 + max_number_of_lines * config_line_size(config_data) + 4;
+    let bit_mask = data
+        .get(bit_mask_start..)
+        .filter(|bit_mask| bit_mask.len() * 8 >= max_number_of_lines)
+        .ok_or(ErrorCode::IndexGreaterThanLength)?;
+
+    Ok((0..max_number_of_lines)
+        .filter(|i| bit_mask[i / 8] & (1 << (7 - i % 8)) != 0)
+        .count())
+}
+
+pub fn get_config_data(a: &AccountInfo) -> core::result::Result<ConfigData, ProgramError> {
+    let config: Config = Config::try_deserialize(&mut a.data.borrow().as_ref())?;
+
+    Ok(config.data)
+}
+
+/// Where the symbol of a config starts, after the discriminator, authority and uuid. The
+/// header getters below read single fields from here on without deserializing the rest.
+const CONFIG_SYMBOL_START: usize = 8 + 32 + 4 + 6;
+
+fn config_seller_fee_start(data: &[u8]) -> usize {
+    let symbol_length = u32::from_le_bytes(*array_ref![data, CONFIG_SYMBOL_START, 4]) as usize;
+    CONFIG_SYMBOL_START + 4 + symbol_length
+}
+
+fn config_is_mutable_start(data: &[u8]) -> usize {
+    let creators_start = config_seller_fee_start(data) + 2;
+    let creators_length = u32::from_le_bytes(*array_ref![data, creators_start, 4]) as usize;
+    creators_start + 4 + creators_length * MAX_CREATOR_LEN + 8
+}
+
+/// Returns the symbol of a config without its zero padding.
+pub fn get_config_symbol(a: &AccountInfo) -> core::result::Result<String, ProgramError> {
+    let data = a.data.borrow();
+    let start = CONFIG_SYMBOL_START + 4;
+    let symbol = std::str::from_utf8(&data[start..config_seller_fee_start(&data)])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+
+    Ok(symbol.trim_end_matches(char::from(0)).to_string())
+}
+
+pub fn get_config_seller_fee(a: &AccountInfo) -> core::result::Result<u16, ProgramError> {
+    let data = a.data.borrow();
+    let start = config_seller_fee_start(&data);
+
+    Ok(u16::from_le_bytes(*array_ref![data, start, 2]))
+}
+
+pub fn get_config_is_mutable(a: &AccountInfo) -> core::result::Result<bool, ProgramError> {
+    let data = a.data.borrow();
+
+    Ok(data[config_is_mutable_start(&data)] != 0)
+}
+
+pub fn get_config_retain_authority(a: &AccountInfo) -> core::result::Result<bool, ProgramError> {
+    let data = a.data.borrow();
+
+    Ok(data[config_is_mutable_start(&data) + 1] != 0)
+}
+
+/// Returns the creators of a config, for tools inspecting royalties.
+pub fn get_config_creators(a: &AccountInfo) -> core::result::Result<Vec<Creator>, ProgramError> {
+    Ok(get_config_data(a)?.creators)
+}
+
+/// Returns how many lines of a config are uploaded and how many it can hold.
+pub fn config_progress(a: &AccountInfo) -> core::result::Result<(u32, u32), ProgramError> {
+    let config_data = get_config_data(a)?;
+    let count = get_config_count(&a.data.borrow())?;
+
+    Ok((count as u32, config_data.max_number_of_lines))
+}
+
+/// Reads every uploaded config line in a single borrow, for tools snapshotting a whole
+/// config.
+pub fn export_config_lines(a: &AccountInfo) -> core::result::Result<Vec<ConfigLine>, ProgramError> {
+    let data = a.data.borrow();
+    let count = get_config_count(&data)?;
+
+    (0..count)
+        .map(|index| decode_config_line(&data, index))
+        .collect()
+}
+
+/// Checks that exactly `expected` lines are uploaded, spot-checking that the first, middle
+/// and last of them aren't blank, so upload jobs can confirm they finished in one call.
+pub fn verify_config_upload(a: &AccountInfo, expected: u32) -> ProgramResult {
+    let data = a.data.borrow();
+    if get_config_count(&data)? != expected as usize {
+        return Err(ErrorCode::ConfigIncomplete.into());
+    }
+    if expected == 0 {
+        return Ok(());
+    }
+
+    let last = expected as usize - 1;
+    for index in [0, last / 2, last] {
+        match decode_config_line(&data, index) {
+            Ok(line) if !line.name.is_empty() && !line.uri.is_empty() => {}
+            _ => return Err(ErrorCode::ConfigIncomplete.into()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that every line below `count` was uploaded, according to the bitmask
+/// `add_config_lines` keeps after the lines.
+pub fn assert_lines_uploaded(data: &[u8], config_data: &ConfigData, count: usize) -> ProgramResult {
+    let bit_mask_start = CONFIG_ARRAY_START
+        + 4
+        + (config_data.max_number_of_lines as usize) * config_line_size(config_data)
+        + 4;
+    let full_bytes = count / 8;
+    let remaining_bits = count % 8;
+    let bit_mask_end = bit_mask_start + full_bytes + if remaining_bits > 0 { 1 } else { 0 };
+    let bit_mask = data
+        .get(bit_mask_start..bit_mask_end)
+        .ok_or(ErrorCode::ConfigIncomplete)?;
+
+    if bit_mask[..full_bytes].iter().any(|byte| *byte != u8::MAX) {
+        return Err(ErrorCode::ConfigIncomplete.into());
+    }
+    if remaining_bits > 0 {
+        let mask = u8::MAX << (8 - remaining_bits);
+        if bit_mask[full_bytes] & mask != mask {
+            return Err(ErrorCode::ConfigIncomplete.into());
+        }
+    }
+
+    Ok(())
+}
+
 pub fn get_config_line(
     a: &AccountInfo,
     index: usize,
 ) -> core::result::Result<ConfigLine, ProgramError> {
-    let arr = a.data.borrow();
+    decode_config_line(&a.data.borrow(), index)
+}
+
+/// Decodes the config line at `index` from the raw bytes of a config account,
+/// so callers holding a plain buffer don't need an `AccountInfo`.
+pub fn decode_config_line(
+    data: &[u8],
+    index: usize,
+) -> core::result::Result<ConfigLine, ProgramError> {
+    let config: Config = Config::try_deserialize(&mut &data[..])?;
+    let config_data = config.data;
+    let line_size = config_line_size(&config_data);
 
-    let total = get_config_count(&arr)?;
-    if index > total {
+    if data.len() < CONFIG_ARRAY_START + 4 {
+        return Err(ErrorCode::IndexGreaterThanLength.into());
+    }
+    let total = u32::from_le_bytes(*array_ref![data, CONFIG_ARRAY_START, 4]) as usize;
+    if index >= total {
         return Err(ErrorCode::IndexGreaterThanLength.into());
     }
-    let data_array = &arr[CONFIG_ARRAY_START + 4 + index * (CONFIG_LINE_SIZE)
-        ..CONFIG_ARRAY_START + 4 + (index + 1) * (CONFIG_LINE_SIZE)];
+    let start = index
+        .checked_mul(line_size)
+        .and_then(|offset| offset.checked_add(CONFIG_ARRAY_START + 4))
+        .ok_or(ErrorCode::NumericalOverflowError)?;
+    let end = start
+        .checked_add(line_size)
+        .ok_or(ErrorCode::NumericalOverflowError)?;
+    // the stored count can't be trusted to fit the account
+    if end > data.len() {
+        return Err(ErrorCode::IndexGreaterThanLength.into());
+    }
+    let data_array = &data[start..end];
+    // lines nobody uploaded are still zeroed, when the config was uploaded with gaps
+    if data_array.iter().all(|byte| *byte == 0) {
+        return Err(ErrorCode::ConfigLineNotUploaded.into());
+    }
+
+    // the strings are stored zero-padded, callers get them without the padding
+    let mut config_line: ConfigLine = match &config_data.name_prefix {
+        Some(prefix) => ConfigLine {
+            name: format!(
+                "{} #{}",
+                prefix,
+                config_data
+                    .name_offset
+                    .checked_add(index as u64 + 1)
+                    .ok_or(ErrorCode::NumericalOverflowError)?
+            ),
+            uri: String::try_from_slice(data_array)?,
+            symbol: None,
+            seller_fee_basis_points: None,
+        }
+        .trimmed(),
+        None => {
+            // lines without a seller fee end in padding, so don't insist on reading it all
+            let mut config_line = ConfigLine::deserialize(&mut &data_array[..])?.trimmed();
+            if config_line.name.is_empty() {
+                return Err(ErrorCode::ConfigLineNotUploaded.into());
+            }
+            if config_line.symbol.as_deref() == Some("") {
+                config_line.symbol = None;
+            }
+            config_line
+        }
+    };
 
-    let config_line: ConfigLine = ConfigLine::try_from_slice(data_array)?;
+    if let Some(base_uri) = &config_data.base_uri {
+        config_line.uri = format!("{}{}", base_uri, config_line.uri);
+    }
 
     Ok(config_line)
 }
 
-pub const CONFIG_LINE_SIZE: usize = 4 + MAX_NAME_LENGTH + 4 + MAX_URI_LENGTH;
+pub const CONFIG_LINE_SIZE: usize =
+    4 + MAX_NAME_LENGTH + 4 + MAX_URI_LENGTH + 1 + 4 + MAX_SYMBOL_LENGTH + 1 + 2;
+pub const COMPACT_CONFIG_LINE_SIZE: usize = 4 + MAX_URI_LENGTH;
+
+pub const MAX_URI_SUFFIX_LENGTH: usize = 32;
+pub const MAX_BASE_URI_LENGTH: usize = MAX_URI_LENGTH - MAX_URI_SUFFIX_LENGTH;
+
+/// Longest uri a config line stores, only the suffix when the config has a base uri.
+pub fn config_uri_length(data: &ConfigData) -> usize {
+    if data.base_uri.is_some() {
+        MAX_URI_SUFFIX_LENGTH
+    } else {
+        MAX_URI_LENGTH
+    }
+}
+
+pub fn config_line_size(data: &ConfigData) -> usize {
+    let line_size = if data.name_prefix.is_some() {
+        COMPACT_CONFIG_LINE_SIZE
+    } else {
+        CONFIG_LINE_SIZE
+    };
+    line_size - MAX_URI_LENGTH + config_uri_length(data)
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Debug)]
 pub struct ConfigLine {
     /// The name of the asset
     pub name: String,
     /// URI pointing to JSON representing the asset
     pub uri: String,
+    /// Overrides the config symbol for this asset, not stored for compact configs
+    pub symbol: Option<String>,
+    /// Overrides the config royalty basis points for this asset, not stored for compact configs
+    pub seller_fee_basis_points: Option<u16>,
+}
+
+impl ConfigLine {
+    /// Strips the zero padding `add_config_lines` stores the strings with.
+    pub fn trimmed(self) -> ConfigLine {
+        let trim = |s: String| s.trim_end_matches(char::from(0)).to_string();
+        ConfigLine {
+            name: trim(self.name),
+            uri: trim(self.uri),
+            symbol: self.symbol.map(trim),
+            seller_fee_basis_points: self.seller_fee_basis_points,
+        }
+    }
+}
+
+#[event]
+pub struct ConfigLinesAdded {
+    pub config: Pubkey,
+    /// Index the batch was written at
+    pub index: u32,
+    /// Number of lines in the batch
+    pub count: u32,
+    /// Number of distinct lines uploaded after this batch
+    pub new_total: u32,
+}
+
+#[event]
+pub struct MintEvent {
+    pub candy_machine: Pubkey,
+    pub mint: Pubkey,
+    pub external_url: Option<String>,
+    /// The config line minted and the name and uri it resolved to, so indexers don't have to
+    /// read the config. Both are trimmed, and token-metadata bounds them to MAX_NAME_LENGTH
+    /// and MAX_URI_LENGTH.
+    pub index: u64,
+    pub name: String,
+    pub uri: String,
+}
+
+#[event]
+pub struct StatsEvent {
+    pub candy_machine: Pubkey,
+    pub items_redeemed: u64,
+    pub items_available: u64,
+    /// See `CandyMachine::total_collected`
+    pub total_collected: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundEvent {
+    pub candy_machine: Pubkey,
+    pub buyer: Pubkey,
+    /// Lamports, or token_mint units in token mode
+    pub amount: u64,
 }
 
 // Unfortunate duplication of token metadata so that IDL picks it up.
@@ -657,4 +2880,138 @@ pub enum ErrorCode {
     CandyMachineNotLiveYet,
     #[msg("Number of config lines must be at least number of items available")]
     ConfigLineMismatch,
+    #[msg("Name prefix leaves no room for the item number within the max name length")]
+    NamePrefixTooLong,
+    #[msg("Recipient token account must hold the freshly minted token for the recipient")]
+    RecipientMismatch,
+    #[msg("Token supplied for burning does not qualify for redemption")]
+    InvalidBurnToken,
+    #[msg("Update authority must be the candy machine authority when authority is retained")]
+    UpdateAuthorityMismatch,
+    #[msg("Update authority must sign when authority is not retained")]
+    UpdateAuthorityMustSign,
+    #[msg("This address is not allowed to mint from this candy machine")]
+    AddressDenied,
+    #[msg("Denylist is full")]
+    DenylistFull,
+    #[msg("Mint authority of the new token must be the mint_authority signer")]
+    MintAuthorityMismatch,
+    #[msg("Freeze authority of the new token must be empty or the mint authority")]
+    FreezeAuthorityMismatch,
+    #[msg("External url is longer than the max uri length")]
+    ExternalUrlTooLong,
+    #[msg("Tip account does not match the candy machine creator fund")]
+    TipAccountMismatch,
+    #[msg("Token burn failed")]
+    TokenBurnFailed,
+    #[msg("Price is above the supplied max price guard")]
+    PriceTooHigh,
+    #[msg("Only holders of the priority collection can mint before the public date")]
+    PriorityHolderRequired,
+    #[msg("Wallet token account is frozen and can't receive payments")]
+    WalletFrozen,
+    #[msg("Uri is longer than a config line can store")]
+    UriTooLong,
+    #[msg("Base uri leaves no room for the uri suffix within the max uri length")]
+    BaseUriTooLong,
+    #[msg("Config line at this index was never uploaded")]
+    ConfigLineNotUploaded,
+    #[msg("Seller fee basis points must be at most 10000")]
+    InvalidBasisPoints,
+    #[msg("Nonce is not a valid proof of work for this candy machine and payer")]
+    InvalidProofOfWork,
+    #[msg("Refund token account must belong to the buyer")]
+    RefundAccountMismatch,
+    #[msg("Token account is not the payer's associated token account for this mint")]
+    AssociatedTokenAccountMismatch,
+    #[msg("Fee payer must sign to cover the price")]
+    FeePayerMustSign,
+    #[msg("Max supply is above the supplied max supply guard")]
+    InvalidMaxSupply,
+    #[msg("Items redeemed can't exceed items available")]
+    RedeemedAboveAvailable,
+    #[msg("Index assignment account is not the one of the index being minted")]
+    IndexAssignmentMismatch,
+    #[msg("This index is assigned to another wallet")]
+    IndexNotAssigned,
+    #[msg("Reveal needs a config that retains authority")]
+    RevealRequiresRetainAuthority,
+    #[msg("Metadata was not minted by this candy machine")]
+    NotMintedByCandyMachine,
+    #[msg("Memo is longer than the max memo length")]
+    MemoTooLong,
+    #[msg("Memo program account is not the SPL memo program")]
+    MemoProgramMismatch,
+    #[msg("Config lines can't change once minting started")]
+    ConfigFrozen,
+    #[msg("Escrow token account must hold this mint and belong to the launchpad")]
+    LaunchpadEscrowMismatch,
+    #[msg("Relayer must be the candy machine's relayer and sign")]
+    RelayerMismatch,
+    #[msg("Candy machine balance can't cover the relayer reimbursement")]
+    RelayerReimbursementUnavailable,
+    #[msg("Supply cap account is not the candy machine's supply cap")]
+    SupplyCapMismatch,
+    #[msg("Metadata or master edition account already exists")]
+    MetadataAlreadyExists,
+    #[msg("Held proceeds can't be released until the withdraw delay after go live elapsed")]
+    WithdrawDelayNotElapsed,
+    #[msg("Mint is not authorized by a signature of the candy machine authority")]
+    InvalidMintAuthorization,
+    #[msg("Candy machine has no go live date, only its authority can mint")]
+    CandyMachineNotConfigured,
+    #[msg("Candy machine belongs to another config")]
+    CandyMachineConfigMismatch,
+    #[msg("Config upload is incomplete or has blank lines")]
+    ConfigIncomplete,
+    #[msg("Payment vault isn't the payer's vault")]
+    PaymentVaultMismatch,
+    #[msg("Too many config lines in one batch")]
+    BatchTooLarge,
+    #[msg("Not enough remaining accounts were passed for this instruction")]
+    MissingTokenAccounts,
+    #[msg("Amount must be a decimal number with at most the token's decimals")]
+    InvalidUiAmount,
+    #[msg("Discount basis points must be at most 10000")]
+    DiscountTooHigh,
+    #[msg("Only holders of the discount collection get the holder discount")]
+    HolderDiscountRequired,
+    #[msg("Withdrawal would leave the config below rent exemption")]
+    WithdrawBelowRentExempt,
+    #[msg("Wallet mints account is not the payer's for this candy machine")]
+    WalletMintsMismatch,
+    #[msg("Wallet reached its mint limit for this window")]
+    WalletMintLimitReached,
+    #[msg("Derived key invalid")]
+    DerivedKeyInvalid,
+    #[msg("Too many mints in this slot, try again")]
+    MintingTooFast,
+    #[msg("Pass token account must hold the pass mint and belong to the payer")]
+    PassTokenMismatch,
+    #[msg("Wallet minted as many items as it holds passes")]
+    PassAllocationReached,
+    #[msg("Rent recipient must be the system account passed to receive the funds")]
+    InvalidRentRecipient,
+    #[msg("Name is longer than token-metadata allows")]
+    NameTooLong,
+    #[msg("Refund window of this mint has closed")]
+    RefundWindowClosed,
+    #[msg("Mint is still refundable")]
+    RefundWindowOpen,
+    #[msg("Symbol is longer than token-metadata allows")]
+    SymbolTooLong,
+    #[msg("Withdraw delay only holds SOL prices, token payments can't be used with it")]
+    WithdrawDelayNeedsSolPrice,
+    #[msg("Mint authorization has expired")]
+    MintAuthorizationExpired,
+    #[msg("Mint authorization was already used")]
+    MintAuthorizationUsed,
+    #[msg("Refund window only holds SOL prices, token payments can't be used with it")]
+    RefundWindowNeedsSolPrice,
+    #[msg("Go live date can't change while proceeds are held for the withdraw delay")]
+    GoLiveDateLocked,
+    #[msg("Wallet of SOL payments must be a system account")]
+    SolWalletMustBeSystemAccount,
+    #[msg("Relayer reimbursement is above the fees of a mint")]
+    RelayerReimbursementTooHigh,
 }
@@ -0,0 +1,129 @@
+//! Builders for testing integrations with the candy machine without assembling every
+//! account by hand. Compiled for unit tests and with the `test-utils` feature.
+
+use {
+    crate::{
+        config_line_size, config_uri_length, CandyMachineData, Config, ConfigData, ConfigLine,
+        CONFIG_ARRAY_START, PREFIX,
+    },
+    anchor_lang::{prelude::Pubkey, AnchorSerialize, Discriminator},
+    metaplex_token_metadata::state::{
+        EDITION, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, PREFIX as METADATA_PREFIX,
+    },
+};
+
+/// Builds config data with `max_number_of_lines` lines and otherwise default settings.
+pub fn make_config_data(max_number_of_lines: u32) -> ConfigData {
+    ConfigData {
+        uuid: "123456".to_string(),
+        symbol: puffed_out("SYMBOL", MAX_SYMBOL_LENGTH),
+        seller_fee_basis_points: 500,
+        max_number_of_lines,
+        defer_creator_verification: false,
+        ..ConfigData::default()
+    }
+}
+
+/// Builds candy machine data selling `items_available` items at `price`, live from the start.
+pub fn make_candy_machine_data(price: u64, items_available: u64) -> CandyMachineData {
+    CandyMachineData {
+        uuid: "123456".to_string(),
+        price,
+        items_available,
+        go_live_date: Some(0),
+    }
+}
+
+/// Builds the raw bytes of a config account the way `initialize_config` and
+/// `add_config_lines` lay them out, with `lines` uploaded from index 0.
+pub fn make_config_account_data(data: ConfigData, lines: &[(&str, &str)]) -> Vec<u8> {
+    let line_size = config_line_size(&data);
+    let max_number_of_lines = data.max_number_of_lines as usize;
+    let mut account_data = vec![
+        0u8;
+        CONFIG_ARRAY_START
+            + 4
+            + max_number_of_lines * line_size
+            + 4
+            + max_number_of_lines / 8
+            + 1
+    ];
+
+    let mut header = Config::discriminator().to_vec();
+    header.append(
+        &mut Config {
+            authority: Pubkey::default(),
+            data: data.clone(),
+            frozen: false,
+        }
+        .try_to_vec()
+        .unwrap(),
+    );
+    account_data[..header.len()].copy_from_slice(&header);
+    account_data[CONFIG_ARRAY_START..CONFIG_ARRAY_START + 4]
+        .copy_from_slice(&(lines.len() as u32).to_le_bytes());
+
+    let bit_mask_start = CONFIG_ARRAY_START + 4 + max_number_of_lines * line_size + 4;
+    for i in 0..lines.len() {
+        account_data[bit_mask_start + i / 8] |= 1 << (7 - i % 8);
+    }
+
+    for (i, (name, uri)) in lines.iter().enumerate() {
+        let uri = puffed_out(uri, config_uri_length(&data));
+        let line = if data.name_prefix.is_some() {
+            uri.try_to_vec().unwrap()
+        } else {
+            ConfigLine {
+                name: puffed_out(name, MAX_NAME_LENGTH),
+                uri,
+                symbol: Some(puffed_out("", MAX_SYMBOL_LENGTH)),
+                seller_fee_basis_points: None,
+            }
+            .try_to_vec()
+            .unwrap()
+        };
+        let start = CONFIG_ARRAY_START + 4 + i * line_size;
+        account_data[start..start + line.len()].copy_from_slice(&line);
+    }
+
+    account_data
+}
+
+/// Derives the candy machine address and bump for `config` and `uuid`.
+pub fn find_candy_machine_address(config: &Pubkey, uuid: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[PREFIX.as_bytes(), config.as_ref(), uuid.as_bytes()],
+        &crate::id(),
+    )
+}
+
+/// Derives the metadata address of `mint`.
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    let program_id = metaplex_token_metadata::id();
+    Pubkey::find_program_address(
+        &[
+            METADATA_PREFIX.as_bytes(),
+            program_id.as_ref(),
+            mint.as_ref(),
+        ],
+        &program_id,
+    )
+}
+
+/// Derives the master edition address of `mint`.
+pub fn find_master_edition_address(mint: &Pubkey) -> (Pubkey, u8) {
+    let program_id = metaplex_token_metadata::id();
+    Pubkey::find_program_address(
+        &[
+            METADATA_PREFIX.as_bytes(),
+            program_id.as_ref(),
+            mint.as_ref(),
+            EDITION.as_bytes(),
+        ],
+        &program_id,
+    )
+}
+
+fn puffed_out(s: &str, size: usize) -> String {
+    s.to_string() + &"\u{0}".repeat(size - s.len())
+}
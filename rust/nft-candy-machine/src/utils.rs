@@ -1,12 +1,26 @@
 use {
-    crate::ErrorCode,
+    crate::{
+        CandyMachine, Config, ConfigLine, ErrorCode, IndexAssignment, MintAuthorization,
+        MintEscrow, MintState, RateLimit, SupplyBreakdown, SupplyCap, UsedMintAuthorization,
+        WalletMints, MINT_AUTHORIZATION, MINT_ESCROW, PAYMENT_VAULT, PREFIX, WALLET_MINTS,
+    },
     anchor_lang::{
-        prelude::{AccountInfo, ProgramError, ProgramResult, Pubkey},
+        prelude::{msg, AccountInfo, ProgramError, ProgramResult, Pubkey},
         solana_program::{
+            hash::hashv,
+            instruction::Instruction,
+            log::sol_log_compute_units,
             program::invoke_signed,
             program_pack::{IsInitialized, Pack},
+            system_instruction,
+            sysvar::{self, rent::Rent},
         },
+        AccountDeserialize, AccountSerialize,
+    },
+    metaplex_token_metadata::state::{
+        Creator, Metadata, MAX_CREATOR_LIMIT, MAX_NAME_LENGTH, MAX_URI_LENGTH,
     },
+    spl_token::state::{Account, Mint},
 };
 
 pub fn assert_initialized<T: Pack + IsInitialized>(
@@ -27,6 +41,140 @@ pub fn assert_owned_by(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
         Ok(())
     }
 }
+
+pub fn assert_derivation(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    path: &[&[u8]],
+) -> Result<u8, ProgramError> {
+    let (key, bump) = Pubkey::find_program_address(path, program_id);
+    if key != *account.key {
+        return Err(ErrorCode::DerivedKeyInvalid.into());
+    }
+    Ok(bump)
+}
+
+/// Checks that `mint_info` belongs to the token program passed alongside it. Only the classic
+/// token program is accepted for now, so a mint of another token program such as Token-2022
+/// fails here instead of deep inside the metadata CPI.
+pub fn assert_mint_token_program(
+    mint_info: &AccountInfo,
+    token_program_info: &AccountInfo,
+) -> ProgramResult {
+    if mint_info.owner != token_program_info.key {
+        return Err(ErrorCode::MintMismatch.into());
+    }
+
+    Ok(())
+}
+
+/// Creates the program owned escrow of `mint` at its PDA, with `payer` funding its rent, and
+/// returns its bump. The caller writes the escrow once the price is paid into it.
+pub fn create_mint_escrow<'a>(
+    escrow_info: &AccountInfo<'a>,
+    mint: &Pubkey,
+    payer_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    rent: &Rent,
+) -> Result<u8, ProgramError> {
+    let bump = assert_derivation(
+        &crate::id(),
+        escrow_info,
+        &[PREFIX.as_bytes(), MINT_ESCROW.as_bytes(), mint.as_ref()],
+    )?;
+    let space = 8 + MintEscrow::LEN;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer_info.key,
+            escrow_info.key,
+            rent.minimum_balance(space),
+            space as u64,
+            &crate::id(),
+        ),
+        &[
+            payer_info.clone(),
+            escrow_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&[
+            PREFIX.as_bytes(),
+            MINT_ESCROW.as_bytes(),
+            mint.as_ref(),
+            &[bump],
+        ]],
+    )?;
+
+    Ok(bump)
+}
+
+/// Creates the record of a used mint authorization at its PDA, with `payer_info` funding its
+/// rent. The record exists as long as the candy machine mints, so a nonce can't be used twice.
+pub fn record_mint_authorization<'a>(
+    record_info: &AccountInfo<'a>,
+    candy_machine: &Pubkey,
+    payer_info: &AccountInfo<'a>,
+    authorization: &MintAuthorization,
+    system_program_info: &AccountInfo<'a>,
+    rent: &Rent,
+) -> ProgramResult {
+    let nonce = authorization.nonce.to_le_bytes();
+    let bump = assert_derivation(
+        &crate::id(),
+        record_info,
+        &[
+            PREFIX.as_bytes(),
+            MINT_AUTHORIZATION.as_bytes(),
+            candy_machine.as_ref(),
+            &nonce,
+        ],
+    )?;
+    if !record_info.data_is_empty() {
+        return Err(ErrorCode::MintAuthorizationUsed.into());
+    }
+
+    let space = 8 + UsedMintAuthorization::LEN;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer_info.key,
+            record_info.key,
+            rent.minimum_balance(space),
+            space as u64,
+            &crate::id(),
+        ),
+        &[
+            payer_info.clone(),
+            record_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&[
+            PREFIX.as_bytes(),
+            MINT_AUTHORIZATION.as_bytes(),
+            candy_machine.as_ref(),
+            &nonce,
+            &[bump],
+        ]],
+    )?;
+
+    let record = UsedMintAuthorization {
+        candy_machine: *candy_machine,
+        payer: *payer_info.key,
+        nonce: authorization.nonce,
+        expires_at: authorization.expires_at,
+    };
+    record.try_serialize(&mut &mut record_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+/// Checks that at least `n` accounts are left in `accounts`, so indexing into them by position
+/// fails with an error instead of a panic.
+pub fn expect_remaining(accounts: &[AccountInfo], n: usize) -> Result<(), ProgramError> {
+    if accounts.len() < n {
+        return Err(ErrorCode::MissingTokenAccounts.into());
+    }
+
+    Ok(())
+}
 ///TokenTransferParams
 pub struct TokenTransferParams<'a: 'b, 'b> {
     /// source
@@ -69,3 +217,637 @@ pub fn spl_token_transfer(params: TokenTransferParams<'_, '_>) -> ProgramResult
 
     result.map_err(|_| ErrorCode::TokenTransferFailed.into())
 }
+
+///TokenBurnParams
+pub struct TokenBurnParams<'a: 'b, 'b> {
+    /// mint
+    pub mint: AccountInfo<'a>,
+    /// source
+    pub source: AccountInfo<'a>,
+    /// amount
+    pub amount: u64,
+    /// authority
+    pub authority: AccountInfo<'a>,
+    /// authority_signer_seeds
+    pub authority_signer_seeds: &'b [&'b [u8]],
+    /// token_program
+    pub token_program: AccountInfo<'a>,
+}
+
+#[inline(always)]
+pub fn spl_token_burn(params: TokenBurnParams<'_, '_>) -> ProgramResult {
+    let TokenBurnParams {
+        mint,
+        source,
+        authority,
+        token_program,
+        amount,
+        authority_signer_seeds,
+    } = params;
+
+    let result = invoke_signed(
+        &spl_token::instruction::burn(
+            token_program.key,
+            source.key,
+            mint.key,
+            authority.key,
+            &[],
+            amount,
+        )?,
+        &[source, mint, authority, token_program],
+        &[authority_signer_seeds],
+    );
+
+    result.map_err(|_| ErrorCode::TokenBurnFailed.into())
+}
+
+/// Checks that `metadata_info` is the token-metadata account of `mint` and that `creator`
+/// has verified it, which is what qualifies an NFT for burn-to-redeem.
+pub fn assert_redeemable_metadata(
+    metadata_info: &AccountInfo,
+    mint: &Pubkey,
+    creator: &Pubkey,
+) -> ProgramResult {
+    if metadata_info.owner != &metaplex_token_metadata::id() {
+        return Err(ErrorCode::InvalidBurnToken.into());
+    }
+
+    let metadata = Metadata::from_account_info(metadata_info)?;
+    if metadata.mint != *mint {
+        return Err(ErrorCode::InvalidBurnToken.into());
+    }
+
+    let verified = match &metadata.data.creators {
+        Some(creators) => creators.iter().any(|c| c.verified && c.address == *creator),
+        None => false,
+    };
+    if !verified {
+        return Err(ErrorCode::InvalidBurnToken.into());
+    }
+
+    Ok(())
+}
+
+/// Checks that `owner` holds, in `token_account_info`, a token carrying `collection` as a
+/// verified creator according to `metadata_info`, failing with `error` otherwise.
+pub fn assert_collection_holder(
+    token_account_info: &AccountInfo,
+    metadata_info: &AccountInfo,
+    owner: &Pubkey,
+    collection: &Pubkey,
+    error: ErrorCode,
+) -> ProgramResult {
+    assert_owned_by(token_account_info, &spl_token::id())?;
+    let token_account: Account = assert_initialized(token_account_info)?;
+    if token_account.owner != *owner || token_account.amount < 1 {
+        return Err(error.into());
+    }
+
+    assert_redeemable_metadata(metadata_info, &token_account.mint, collection)
+        .map_err(|_| error.into())
+}
+
+/// Checks that `payer` may mint the config line at `index`, i.e. that the authority didn't
+/// assign it to another wallet. `assignment_info` is the assignment PDA of that index, which
+/// stays empty while the line is unassigned.
+pub fn assert_index_assignee(
+    assignment_info: &AccountInfo,
+    candy_machine: &Pubkey,
+    index: u64,
+    payer: &Pubkey,
+) -> ProgramResult {
+    let (assignment_key, _) = Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            candy_machine.as_ref(),
+            &index.to_le_bytes(),
+        ],
+        &crate::id(),
+    );
+    if *assignment_info.key != assignment_key {
+        return Err(ErrorCode::IndexAssignmentMismatch.into());
+    }
+    if assignment_info.data_is_empty() {
+        return Ok(());
+    }
+
+    assert_owned_by(assignment_info, &crate::id())?;
+    let assignment = IndexAssignment::try_deserialize(&mut &assignment_info.data.borrow()[..])?;
+    if assignment.wallet != *payer {
+        return Err(ErrorCode::IndexNotAssigned.into());
+    }
+
+    Ok(())
+}
+
+/// Counts one more mint against the supply cap in `supply_cap_info`, failing like an empty
+/// candy machine once the cap is reached.
+pub fn count_against_supply_cap(
+    supply_cap_info: &AccountInfo,
+    supply_cap: &Pubkey,
+) -> ProgramResult {
+    if supply_cap_info.key != supply_cap {
+        return Err(ErrorCode::SupplyCapMismatch.into());
+    }
+    assert_owned_by(supply_cap_info, &crate::id())?;
+
+    let mut cap = SupplyCap::try_deserialize(&mut &supply_cap_info.data.borrow()[..])?;
+    if cap.minted >= cap.max {
+        return Err(ErrorCode::CandyMachineEmpty.into());
+    }
+    cap.minted = cap
+        .minted
+        .checked_add(1)
+        .ok_or(ErrorCode::NumericalOverflowError)?;
+    cap.try_serialize(&mut &mut supply_cap_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+/// Reads the metadata in `metadata_info`, checking it is the metadata of an item minted by
+/// `candy_machine`, which is the first creator of everything it mints, verified or not.
+pub fn assert_minted_by(
+    metadata_info: &AccountInfo,
+    candy_machine: &Pubkey,
+) -> Result<Metadata, ProgramError> {
+    assert_owned_by(metadata_info, &metaplex_token_metadata::id())?;
+    let metadata = Metadata::from_account_info(metadata_info)?;
+    let minted_here = match &metadata.data.creators {
+        Some(creators) => creators.first().map(|c| c.address) == Some(*candy_machine),
+        None => false,
+    };
+    if !minted_here {
+        return Err(ErrorCode::NotMintedByCandyMachine.into());
+    }
+
+    Ok(metadata)
+}
+
+/// Checks that `vault` is the payment vault of `payer` with bump `bump`.
+pub fn assert_payment_vault(vault: &Pubkey, payer: &Pubkey, bump: u8) -> ProgramResult {
+    let expected = Pubkey::create_program_address(
+        &[
+            PREFIX.as_bytes(),
+            PAYMENT_VAULT.as_bytes(),
+            payer.as_ref(),
+            &[bump],
+        ],
+        &crate::id(),
+    )
+    .map_err(|_| ErrorCode::PaymentVaultMismatch)?;
+    if *vault != expected {
+        return Err(ErrorCode::PaymentVaultMismatch.into());
+    }
+
+    Ok(())
+}
+
+/// Returns how many passes of `pass_mint` `owner` holds in `pass_token_info`.
+pub fn assert_pass_balance(
+    pass_token_info: &AccountInfo,
+    owner: &Pubkey,
+    pass_mint: &Pubkey,
+) -> Result<u64, ProgramError> {
+    assert_owned_by(pass_token_info, &spl_token::id())?;
+    let pass_token: Account = assert_initialized(pass_token_info)?;
+    if pass_token.owner != *owner || pass_token.mint != *pass_mint {
+        return Err(ErrorCode::PassTokenMismatch.into());
+    }
+
+    Ok(pass_token.amount)
+}
+
+/// Counts a mint of `payer` in its wallet mints PDA for `candy_machine`, see
+/// `count_wallet_mint`.
+pub fn count_wallet_mints(
+    wallet_mints_info: &AccountInfo,
+    candy_machine: &Pubkey,
+    payer: &Pubkey,
+    rate_limit: Option<&RateLimit>,
+    allocation: Option<u64>,
+    now: i64,
+) -> ProgramResult {
+    let (wallet_mints_key, _) = Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            WALLET_MINTS.as_bytes(),
+            candy_machine.as_ref(),
+            payer.as_ref(),
+        ],
+        &crate::id(),
+    );
+    if *wallet_mints_info.key != wallet_mints_key {
+        return Err(ErrorCode::WalletMintsMismatch.into());
+    }
+    assert_owned_by(wallet_mints_info, &crate::id())?;
+
+    let mut wallet_mints = WalletMints::try_deserialize(&mut &wallet_mints_info.data.borrow()[..])?;
+    count_wallet_mint(&mut wallet_mints, rate_limit, allocation, now)?;
+    wallet_mints.try_serialize(&mut &mut wallet_mints_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+/// Counts one mint in `wallet_mints`, failing once the payer reached `rate_limit` in the
+/// current window, starting a new window first when the last one is over, or once its total
+/// reached `allocation`.
+pub fn count_wallet_mint(
+    wallet_mints: &mut WalletMints,
+    rate_limit: Option<&RateLimit>,
+    allocation: Option<u64>,
+    now: i64,
+) -> ProgramResult {
+    if let Some(rate_limit) = rate_limit {
+        let window_end = wallet_mints
+            .window_start
+            .checked_add(rate_limit.window_seconds)
+            .ok_or(ErrorCode::NumericalOverflowError)?;
+        if now >= window_end {
+            wallet_mints.window_start = now;
+            wallet_mints.minted = 0;
+        }
+
+        if wallet_mints.minted >= rate_limit.max_mints {
+            return Err(ErrorCode::WalletMintLimitReached.into());
+        }
+        wallet_mints.minted += 1;
+    }
+
+    if let Some(allocation) = allocation {
+        if u64::from(wallet_mints.total_minted) >= allocation {
+            return Err(ErrorCode::PassAllocationReached.into());
+        }
+    }
+    wallet_mints.total_minted = wallet_mints
+        .total_minted
+        .checked_add(1)
+        .ok_or(ErrorCode::NumericalOverflowError)?;
+
+    Ok(())
+}
+
+/// Checks that `nonce` is a proof of work for `payer` minting from `candy_machine`, that is
+/// sha256(candy_machine, payer, nonce as u64 le) starts with `difficulty` zero bits.
+pub fn assert_proof_of_work(
+    candy_machine: &Pubkey,
+    payer: &Pubkey,
+    nonce: u64,
+    difficulty: u8,
+) -> ProgramResult {
+    let hash = hashv(&[candy_machine.as_ref(), payer.as_ref(), &nonce.to_le_bytes()]);
+
+    let mut zero_bits = 0;
+    for byte in hash.as_ref() {
+        zero_bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+
+    if zero_bits < difficulty as u32 {
+        return Err(ErrorCode::InvalidProofOfWork.into());
+    }
+
+    Ok(())
+}
+
+/// The native program verifying ed25519 signatures, which solana-program 1.7 doesn't export.
+pub mod ed25519_program {
+    anchor_lang::declare_id!("Ed25519SigVerify111111111111111111111111111");
+}
+
+/// Checks that `authorization` hasn't expired at `now` and that an instruction before the
+/// current one in the instructions sysvar verifies a signature of `authority` over the
+/// `candy_machine` and `payer` keys followed by the little endian nonce and expiry.
+pub fn assert_mint_authorization(
+    instructions_info: &AccountInfo,
+    authority: &Pubkey,
+    candy_machine: &Pubkey,
+    payer: &Pubkey,
+    authorization: &MintAuthorization,
+    now: i64,
+) -> ProgramResult {
+    if now > authorization.expires_at {
+        return Err(ErrorCode::MintAuthorizationExpired.into());
+    }
+    if *instructions_info.key != sysvar::instructions::id() {
+        return Err(ErrorCode::InvalidMintAuthorization.into());
+    }
+
+    let data = instructions_info.data.borrow();
+    let message = [
+        candy_machine.as_ref(),
+        payer.as_ref(),
+        &authorization.nonce.to_le_bytes(),
+        &authorization.expires_at.to_le_bytes(),
+    ]
+    .concat();
+    for index in 0..sysvar::instructions::load_current_index(&data) {
+        let instruction = sysvar::instructions::load_instruction_at(index as usize, &data)
+            .map_err(|_| ErrorCode::InvalidMintAuthorization)?;
+        if assert_ed25519_authorization(&instruction, authority, &message).is_ok() {
+            return Ok(());
+        }
+    }
+
+    Err(ErrorCode::InvalidMintAuthorization.into())
+}
+
+/// Checks that `instruction` makes the ed25519 program verify one signature of `authority`
+/// over `message`, with the key and the message inside the instruction data itself.
+pub fn assert_ed25519_authorization(
+    instruction: &Instruction,
+    authority: &Pubkey,
+    message: &[u8],
+) -> ProgramResult {
+    let data = &instruction.data;
+    // signature count and padding, then the signature, public key and message offsets,
+    // each followed by the index of the instruction holding it, u16::MAX being this one
+    if instruction.program_id != ed25519_program::id() || data.len() < 16 || data[0] != 1 {
+        return Err(ErrorCode::InvalidMintAuthorization.into());
+    }
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let public_key_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    if read_u16(8) != u16::MAX || read_u16(14) != u16::MAX {
+        return Err(ErrorCode::InvalidMintAuthorization.into());
+    }
+
+    let public_key = data.get(public_key_offset..public_key_offset + 32);
+    let signed_message = data.get(message_offset..message_offset + message_size);
+    if public_key != Some(authority.as_ref()) || signed_message != Some(message) {
+        return Err(ErrorCode::InvalidMintAuthorization.into());
+    }
+
+    Ok(())
+}
+
+/// Checks that `wallet` is a token account for the mint in `token_mint_info`, so it can
+/// collect token payments. A frozen wallet would make every mint fail, so it is rejected.
+/// Returns the decimals of the mint, which are those of every token the wallet receives.
+pub fn assert_token_wallet(
+    wallet: &AccountInfo,
+    token_mint_info: &AccountInfo,
+) -> Result<u8, ProgramError> {
+    let token_mint: Mint = assert_initialized(token_mint_info)?;
+    let token_account: Account = assert_initialized(wallet)?;
+
+    assert_owned_by(token_mint_info, &spl_token::id())?;
+    assert_owned_by(wallet, &spl_token::id())?;
+
+    if token_account.mint != *token_mint_info.key {
+        return Err(ErrorCode::MintMismatch.into());
+    }
+
+    if token_account.is_frozen() {
+        return Err(ErrorCode::WalletFrozen.into());
+    }
+
+    Ok(token_mint.decimals)
+}
+
+/// Rejects `price` when it is above the opt-in `max_price_guard`, catching a mistyped
+/// price before buyers are charged it.
+pub fn assert_price_within_guard(price: u64, max_price_guard: Option<u64>) -> ProgramResult {
+    match max_price_guard {
+        Some(max_price) if price > max_price => Err(ErrorCode::PriceTooHigh.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Rejects `max_supply` when it is above the opt-in `max_supply_guard`, so a config meant
+/// for unique items can't end up allowing unlimited prints.
+pub fn assert_max_supply_within_guard(
+    max_supply: u64,
+    max_supply_guard: Option<u64>,
+) -> ProgramResult {
+    match max_supply_guard {
+        Some(max) if max_supply > max => Err(ErrorCode::InvalidMaxSupply.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Resolves who becomes the update authority of a minted item, and whether
+/// `update_authority_info` has to sign for it. A candy machine retaining authority keeps it
+/// for its own authority, nobody signing, otherwise whoever takes the metadata over signs.
+pub fn resolve_update_authority(
+    config: &Config,
+    candy_machine: &CandyMachine,
+    update_authority_info: &AccountInfo,
+) -> (Pubkey, bool) {
+    if config.data.retain_authority {
+        (candy_machine.authority, false)
+    } else {
+        (*update_authority_info.key, true)
+    }
+}
+
+/// Checks that the decoded `line` fits token-metadata's limits. `add_config_lines` bounds what
+/// is stored, but generated names and base uris are only assembled on decode, and a line that
+/// is too long would otherwise fail inside the metadata CPI.
+pub fn assert_metadata_lengths(line: &ConfigLine) -> ProgramResult {
+    if line.name.len() > MAX_NAME_LENGTH {
+        return Err(ErrorCode::NameTooLong.into());
+    }
+    if line.uri.len() > MAX_URI_LENGTH {
+        return Err(ErrorCode::UriTooLong.into());
+    }
+
+    Ok(())
+}
+
+/// Builds the creators of a minted item: the candy machine, then the config creators.
+/// `initialize_config` bounds the config creators, but the metadata CPI fails obscurely on
+/// too many of them, so they are checked again here.
+pub fn mint_creators(
+    candy_machine: &Pubkey,
+    config: &Config,
+) -> Result<Vec<Creator>, ProgramError> {
+    // - 1 because the candy machine is a creator
+    if config.data.creators.len() > MAX_CREATOR_LIMIT - 1 {
+        return Err(ErrorCode::TooManyCreators.into());
+    }
+
+    // with deferred verification the authority verifies later with sign_metadata_batch
+    let mut creators = vec![Creator {
+        address: *candy_machine,
+        verified: !config.data.defer_creator_verification,
+        share: 0,
+    }];
+    for c in &config.data.creators {
+        creators.push(Creator {
+            address: c.address,
+            verified: false,
+            share: c.share,
+        });
+    }
+
+    Ok(creators)
+}
+
+/// Resolves what a buyer pays for their next mint.
+/// Every pricing rule belongs here so `mint_nft` and clients agree on the amount;
+/// today that is the flat `price`, charged in lamports or in `token_mint` units, less the
+/// holder discount for buyers proven to hold the discount collection.
+pub fn resolve_price(candy_machine: &CandyMachine, is_holder: bool) -> Result<u64, ProgramError> {
+    let price = candy_machine.data.price;
+    match candy_machine.holder_discount {
+        Some(discount) if is_holder => {
+            let cut = (price as u128 * discount.discount_basis_points as u128 / 10000) as u64;
+            price
+                .checked_sub(cut)
+                .ok_or_else(|| ErrorCode::NumericalOverflowError.into())
+        }
+        _ => Ok(price),
+    }
+}
+
+/// Converts a UI amount such as `"1.5"` into the raw amount of a token with `decimals`
+/// decimals, which is what `price` holds for token payments. Parsed without floats so the
+/// result is exact, amounts with more fractional digits than the token has are rejected.
+pub fn ui_amount_to_raw(ui_amount: &str, decimals: u8) -> Result<u64, ProgramError> {
+    let (whole, fraction) = ui_amount.split_once('.').unwrap_or((ui_amount, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > decimals as usize
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(ErrorCode::InvalidUiAmount.into());
+    }
+
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    digits
+        .parse::<u64>()
+        .map_err(|_| ErrorCode::NumericalOverflowError.into())
+}
+
+/// Returns the collection whose holders alone may mint at time `now`, set from go live
+/// until `public_date`. Without a public date the priority window never closes.
+pub fn active_priority_collection(cm: &CandyMachine, now: i64) -> Option<Pubkey> {
+    match (
+        cm.mint_options.priority_collection,
+        cm.mint_options.public_date,
+    ) {
+        (Some(_), Some(public_date)) if now >= public_date => None,
+        (collection, _) => collection,
+    }
+}
+
+/// Checks that a candy machine and the config at `config_key` agree with each other, returning
+/// the first inconsistency found. Meant as a health check for clients as well as `mint_nft`.
+pub fn validate_candy_machine(
+    candy_machine: &CandyMachine,
+    config_key: &Pubkey,
+    config: &Config,
+) -> ProgramResult {
+    if candy_machine.config != *config_key {
+        return Err(ErrorCode::CandyMachineConfigMismatch.into());
+    }
+    if candy_machine.items_redeemed > candy_machine.data.items_available {
+        return Err(ErrorCode::RedeemedAboveAvailable.into());
+    }
+    if candy_machine.data.items_available > config.data.max_number_of_lines as u64 {
+        return Err(ErrorCode::ConfigLineMismatch.into());
+    }
+    // - 1 because the candy machine is a creator
+    if config.data.creators.len() > MAX_CREATOR_LIMIT - 1 {
+        return Err(ErrorCode::TooManyCreators.into());
+    }
+
+    Ok(())
+}
+
+/// Takes one item off the supply of `candy_machine` for a mint, failing once every item is
+/// redeemed. Every mint path goes through here so they all count alike.
+pub fn consume_one(candy_machine: &mut CandyMachine) -> ProgramResult {
+    if candy_machine.items_redeemed >= candy_machine.data.items_available {
+        return Err(ErrorCode::CandyMachineEmpty.into());
+    }
+    candy_machine.items_redeemed = candy_machine
+        .items_redeemed
+        .checked_add(1)
+        .ok_or(ErrorCode::NumericalOverflowError)?;
+
+    Ok(())
+}
+
+/// Counts a mint in `slot` against the candy machine's `max_per_slot`, starting the count over
+/// once the slot advanced.
+pub fn count_against_throttle(candy_machine: &mut CandyMachine, slot: u64) -> ProgramResult {
+    let max_per_slot = match candy_machine.max_per_slot {
+        Some(max_per_slot) => max_per_slot,
+        None => return Ok(()),
+    };
+
+    if slot != candy_machine.last_mint_slot {
+        candy_machine.last_mint_slot = slot;
+        candy_machine.mints_this_slot = 0;
+    }
+    if candy_machine.mints_this_slot >= max_per_slot {
+        return Err(ErrorCode::MintingTooFast.into());
+    }
+    candy_machine.mints_this_slot += 1;
+
+    Ok(())
+}
+
+/// Whether a candy machine going live at `go_live_date` is live at `now`. Some test
+/// validators report a zeroed clock, which never counts as live rather than as 1970.
+pub fn is_live(go_live_date: Option<i64>, now: i64) -> bool {
+    matches!(go_live_date, Some(go_live) if now > 0 && now >= go_live)
+}
+
+/// Aggregates the public mint gates of a candy machine at time `now`. The authority can
+/// mint before go live, this only reports what everybody else sees.
+pub fn mint_state(cm: &CandyMachine, now: i64) -> MintState {
+    let remaining = cm.data.items_available.saturating_sub(cm.items_redeemed);
+
+    MintState {
+        is_live: is_live(cm.data.go_live_date, now),
+        is_sold_out: remaining == 0,
+        remaining,
+        active_price: cm.data.price,
+    }
+}
+
+/// Splits the supply of a candy machine for dashboards. `reserved` counts the lines
+/// assign_index reserved and unassign_index did not release, minted or not, so `public_remaining` is a lower bound on what
+/// the public can still mint. Neither goes below zero when more was redeemed or reserved than
+/// is available.
+pub fn supply_breakdown(cm: &CandyMachine) -> SupplyBreakdown {
+    let available = cm.data.items_available;
+    let reserved = cm.assigned_indices as u64;
+    let public_remaining = available
+        .saturating_sub(cm.items_redeemed)
+        .saturating_sub(reserved);
+
+    SupplyBreakdown {
+        available,
+        redeemed: cm.items_redeemed,
+        reserved,
+        public_remaining,
+    }
+}
+
+/// Returns the config line index the next mint would take, `None` once sold out. Lines are
+/// always minted in order, so this is `items_redeemed`. The solana-program this builds
+/// against has no return data, so front ends call this on the fetched account instead.
+pub fn next_mint_index(cm: &CandyMachine) -> Option<u64> {
+    if cm.items_redeemed < cm.data.items_available {
+        Some(cm.items_redeemed)
+    } else {
+        None
+    }
+}
+
+/// Logs `label` followed by the remaining compute units, a no-op unless `enabled`.
+#[inline(always)]
+pub fn log_compute_units(enabled: bool, label: &str) {
+    if enabled {
+        msg!(label);
+        sol_log_compute_units();
+    }
+}
@@ -0,0 +1,1381 @@
+#![cfg(test)]
+
+mod resolve_price_test {
+    use crate::{utils::resolve_price, CandyMachine, CandyMachineData, HolderDiscount};
+
+    fn candy_machine(price: u64) -> CandyMachine {
+        CandyMachine {
+            data: CandyMachineData {
+                uuid: "123456".to_string(),
+                price,
+                items_available: 10,
+                go_live_date: Some(100),
+            },
+            ..CandyMachine::default()
+        }
+    }
+
+    #[test]
+    fn flat_price_test() {
+        for price in &[0, 5, 1_000_000_000] {
+            let result = resolve_price(&candy_machine(*price), false).unwrap();
+            assert_eq!(result, *price);
+        }
+    }
+
+    #[test]
+    fn token_price_test() {
+        let mut machine = candy_machine(7);
+        machine.token_mint = Some(Default::default());
+        let result = resolve_price(&machine, false).unwrap();
+        assert_eq!(result, 7);
+    }
+
+    #[test]
+    fn holder_discount_test() {
+        let mut machine = candy_machine(1_000_000_000);
+        machine.holder_discount = Some(HolderDiscount {
+            collection: Default::default(),
+            discount_basis_points: 2500,
+        });
+
+        let holder_price = resolve_price(&machine, true).unwrap();
+        assert_eq!(holder_price, 750_000_000);
+        let full_price = resolve_price(&machine, false).unwrap();
+        assert_eq!(full_price, 1_000_000_000);
+
+        machine.holder_discount = None;
+        let undiscounted = resolve_price(&machine, true).unwrap();
+        assert_eq!(undiscounted, 1_000_000_000);
+    }
+}
+
+mod ui_amount_to_raw_test {
+    use crate::{utils::ui_amount_to_raw, ErrorCode};
+
+    #[test]
+    fn ui_amount_to_raw_test() {
+        assert_eq!(ui_amount_to_raw("1.5", 6).unwrap(), 1_500_000);
+        assert_eq!(ui_amount_to_raw("2", 6).unwrap(), 2_000_000);
+        assert_eq!(ui_amount_to_raw(".000001", 6).unwrap(), 1);
+        assert_eq!(ui_amount_to_raw("3", 0).unwrap(), 3);
+    }
+
+    #[test]
+    fn invalid_ui_amount_test() {
+        for ui_amount in ["1.0000001", "", ".", "1,5", "-1", "1.2.3"] {
+            assert_eq!(
+                ui_amount_to_raw(ui_amount, 6).unwrap_err(),
+                ErrorCode::InvalidUiAmount.into()
+            );
+        }
+        assert_eq!(
+            ui_amount_to_raw("18446744073709551616", 0).unwrap_err(),
+            ErrorCode::NumericalOverflowError.into()
+        );
+    }
+}
+
+mod mint_state_test {
+    use crate::{utils::mint_state, CandyMachine, CandyMachineData, MintState};
+
+    fn candy_machine(go_live_date: Option<i64>, items_redeemed: u64) -> CandyMachine {
+        CandyMachine {
+            data: CandyMachineData {
+                uuid: "123456".to_string(),
+                price: 5,
+                items_available: 10,
+                go_live_date,
+            },
+            items_redeemed,
+            ..CandyMachine::default()
+        }
+    }
+
+    #[test]
+    fn mint_state_test() {
+        let cases = &[
+            // (go live date, items redeemed, now, is live, is sold out, remaining)
+            (None, 0, 100, false, false, 10),
+            (Some(50), 0, 49, false, false, 10),
+            (Some(50), 3, 50, true, false, 7),
+            (Some(50), 10, 100, true, true, 0),
+            (None, 10, 100, false, true, 0),
+            (Some(50), 12, 100, true, true, 0),
+        ];
+        for (go_live_date, items_redeemed, now, is_live, is_sold_out, remaining) in cases {
+            let result = mint_state(&candy_machine(*go_live_date, *items_redeemed), *now);
+            assert_eq!(
+                result,
+                MintState {
+                    is_live: *is_live,
+                    is_sold_out: *is_sold_out,
+                    remaining: *remaining,
+                    active_price: 5,
+                },
+                "go_live_date: {:?}, items_redeemed: {}, now: {}",
+                go_live_date,
+                items_redeemed,
+                now
+            );
+        }
+    }
+}
+
+mod supply_breakdown_test {
+    use crate::{utils::supply_breakdown, CandyMachine, CandyMachineData, SupplyBreakdown};
+
+    #[test]
+    fn supply_breakdown_test() {
+        let cases = &[
+            // (items available, items redeemed, assigned indices, public remaining)
+            (10, 0, 0, 10),
+            (10, 3, 0, 7),
+            (10, 3, 2, 5),
+            (10, 8, 2, 0),
+            (10, 8, 5, 0),
+            (10, 12, 0, 0),
+            (0, 0, 3, 0),
+            (u64::MAX, 1, u32::MAX, u64::MAX - 1 - u32::MAX as u64),
+        ];
+        for (items_available, items_redeemed, assigned_indices, public_remaining) in cases {
+            let cm = CandyMachine {
+                data: CandyMachineData {
+                    items_available: *items_available,
+                    ..CandyMachineData::default()
+                },
+                items_redeemed: *items_redeemed,
+                assigned_indices: *assigned_indices,
+                ..CandyMachine::default()
+            };
+            assert_eq!(
+                supply_breakdown(&cm),
+                SupplyBreakdown {
+                    available: *items_available,
+                    redeemed: *items_redeemed,
+                    reserved: *assigned_indices as u64,
+                    public_remaining: *public_remaining,
+                },
+                "items_available: {}, items_redeemed: {}, assigned_indices: {}",
+                items_available,
+                items_redeemed,
+                assigned_indices
+            );
+        }
+    }
+}
+
+mod resolve_update_authority_test {
+    use {
+        crate::{
+            test_utils::make_config_data, utils::resolve_update_authority, CandyMachine, Config,
+        },
+        anchor_lang::prelude::{AccountInfo, Pubkey},
+    };
+
+    fn resolve(retain_authority: bool) -> ((Pubkey, bool), Pubkey, Pubkey) {
+        let config = Config {
+            data: crate::ConfigData {
+                retain_authority,
+                ..make_config_data(10)
+            },
+            ..Config::default()
+        };
+        let candy_machine = CandyMachine {
+            authority: Pubkey::new_unique(),
+            ..CandyMachine::default()
+        };
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::default();
+        let mut lamports = 0;
+        let mut data = [];
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        (
+            resolve_update_authority(&config, &candy_machine, &info),
+            candy_machine.authority,
+            key,
+        )
+    }
+
+    #[test]
+    fn retain_authority_test() {
+        let (resolved, authority, _) = resolve(true);
+        assert_eq!(resolved, (authority, false));
+    }
+
+    #[test]
+    fn hand_over_authority_test() {
+        let (resolved, _, update_authority) = resolve(false);
+        assert_eq!(resolved, (update_authority, true));
+    }
+}
+
+mod mint_creators_test {
+    use {
+        crate::{test_utils::make_config_data, utils::mint_creators, Config, Creator, ErrorCode},
+        anchor_lang::prelude::Pubkey,
+        metaplex_token_metadata::state::MAX_CREATOR_LIMIT,
+    };
+
+    fn config(creators: usize) -> Config {
+        let mut data = make_config_data(10);
+        data.creators = (0..creators)
+            .map(|_| Creator {
+                address: Pubkey::new_unique(),
+                verified: false,
+                share: 10,
+            })
+            .collect();
+        Config {
+            data,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn candy_machine_comes_first_test() {
+        let candy_machine = Pubkey::new_unique();
+        let creators = mint_creators(&candy_machine, &config(MAX_CREATOR_LIMIT - 1)).unwrap();
+        assert_eq!(creators.len(), MAX_CREATOR_LIMIT);
+        assert_eq!(creators[0].address, candy_machine);
+        assert!(creators[0].verified);
+        assert!(creators[1..].iter().all(|c| !c.verified));
+    }
+
+    #[test]
+    fn too_many_creators_test() {
+        let result = mint_creators(&Pubkey::new_unique(), &config(MAX_CREATOR_LIMIT));
+        assert_eq!(result.unwrap_err(), ErrorCode::TooManyCreators.into());
+    }
+}
+
+mod validate_candy_machine_test {
+    use {
+        crate::{
+            test_utils::{make_candy_machine_data, make_config_data},
+            utils::validate_candy_machine,
+            CandyMachine, Config, Creator, ErrorCode,
+        },
+        anchor_lang::prelude::Pubkey,
+        metaplex_token_metadata::state::MAX_CREATOR_LIMIT,
+    };
+
+    fn candy_machine_and_config() -> (CandyMachine, Pubkey, Config) {
+        let config_key = Pubkey::new_unique();
+        let candy_machine = CandyMachine {
+            config: config_key,
+            data: make_candy_machine_data(0, 10),
+            items_redeemed: 10,
+            ..CandyMachine::default()
+        };
+        let config = Config {
+            data: make_config_data(10),
+            ..Config::default()
+        };
+        (candy_machine, config_key, config)
+    }
+
+    #[test]
+    fn consistent_test() {
+        let (candy_machine, config_key, config) = candy_machine_and_config();
+        assert!(validate_candy_machine(&candy_machine, &config_key, &config).is_ok());
+    }
+
+    #[test]
+    fn other_config_test() {
+        let (candy_machine, _, config) = candy_machine_and_config();
+        assert_eq!(
+            validate_candy_machine(&candy_machine, &Pubkey::new_unique(), &config).unwrap_err(),
+            ErrorCode::CandyMachineConfigMismatch.into()
+        );
+    }
+
+    #[test]
+    fn redeemed_above_available_test() {
+        let (mut candy_machine, config_key, config) = candy_machine_and_config();
+        candy_machine.items_redeemed = 11;
+        assert_eq!(
+            validate_candy_machine(&candy_machine, &config_key, &config).unwrap_err(),
+            ErrorCode::RedeemedAboveAvailable.into()
+        );
+    }
+
+    #[test]
+    fn available_above_lines_test() {
+        let (mut candy_machine, config_key, config) = candy_machine_and_config();
+        candy_machine.data.items_available = 11;
+        assert_eq!(
+            validate_candy_machine(&candy_machine, &config_key, &config).unwrap_err(),
+            ErrorCode::ConfigLineMismatch.into()
+        );
+    }
+
+    #[test]
+    fn too_many_creators_test() {
+        let (candy_machine, config_key, mut config) = candy_machine_and_config();
+        config.data.creators = (0..MAX_CREATOR_LIMIT)
+            .map(|_| Creator {
+                address: Pubkey::new_unique(),
+                verified: false,
+                share: 0,
+            })
+            .collect();
+        assert_eq!(
+            validate_candy_machine(&candy_machine, &config_key, &config).unwrap_err(),
+            ErrorCode::TooManyCreators.into()
+        );
+    }
+}
+
+mod consume_one_test {
+    use crate::{test_utils::make_candy_machine_data, utils::consume_one, CandyMachine, ErrorCode};
+
+    #[test]
+    fn consume_one_test() {
+        let mut candy_machine = CandyMachine {
+            data: make_candy_machine_data(0, 3),
+            ..CandyMachine::default()
+        };
+
+        for redeemed in 1..=3 {
+            consume_one(&mut candy_machine).unwrap();
+            assert_eq!(candy_machine.items_redeemed, redeemed);
+        }
+        assert_eq!(
+            consume_one(&mut candy_machine).unwrap_err(),
+            ErrorCode::CandyMachineEmpty.into()
+        );
+        assert_eq!(candy_machine.items_redeemed, 3);
+    }
+}
+
+mod expect_remaining_test {
+    use {
+        crate::{utils::expect_remaining, ErrorCode},
+        anchor_lang::prelude::{AccountInfo, Pubkey},
+    };
+
+    #[test]
+    fn expect_remaining_test() {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [];
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        let accounts = vec![info.clone(), info];
+
+        assert!(expect_remaining(&accounts, 2).is_ok());
+        assert!(expect_remaining(&accounts[1..], 1).is_ok());
+        assert_eq!(
+            expect_remaining(&accounts[1..], 2).unwrap_err(),
+            ErrorCode::MissingTokenAccounts.into()
+        );
+        assert_eq!(
+            expect_remaining(&[], 1).unwrap_err(),
+            ErrorCode::MissingTokenAccounts.into()
+        );
+    }
+}
+
+mod metadata_lengths_test {
+    use {
+        crate::{utils::assert_metadata_lengths, ConfigLine, ErrorCode},
+        metaplex_token_metadata::state::{MAX_NAME_LENGTH, MAX_URI_LENGTH},
+    };
+
+    fn line(name_length: usize, uri_length: usize) -> ConfigLine {
+        ConfigLine {
+            name: "n".repeat(name_length),
+            uri: "u".repeat(uri_length),
+            symbol: None,
+            seller_fee_basis_points: None,
+        }
+    }
+
+    #[test]
+    fn metadata_lengths_test() {
+        assert!(assert_metadata_lengths(&line(MAX_NAME_LENGTH, MAX_URI_LENGTH)).is_ok());
+        assert_eq!(
+            assert_metadata_lengths(&line(MAX_NAME_LENGTH + 1, 1)).unwrap_err(),
+            ErrorCode::NameTooLong.into()
+        );
+        assert_eq!(
+            assert_metadata_lengths(&line(1, MAX_URI_LENGTH + 1)).unwrap_err(),
+            ErrorCode::UriTooLong.into()
+        );
+    }
+}
+
+mod mint_token_program_test {
+    use {
+        crate::{utils::assert_mint_token_program, ErrorCode},
+        anchor_lang::prelude::{AccountInfo, Pubkey},
+        std::str::FromStr,
+    };
+
+    #[test]
+    fn mint_token_program_test() {
+        let token_program = spl_token::id();
+        let token_2022 = Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb").unwrap();
+        let mint = Pubkey::new_unique();
+        let loader = Pubkey::new_unique();
+        let (mut program_lamports, mut mint_lamports) = (0, 0);
+        let (mut program_data, mut mint_data) = ([], []);
+        let program_info = AccountInfo::new(
+            &token_program,
+            false,
+            false,
+            &mut program_lamports,
+            &mut program_data,
+            &loader,
+            true,
+            0,
+        );
+        let mut mint_info = AccountInfo::new(
+            &mint,
+            false,
+            true,
+            &mut mint_lamports,
+            &mut mint_data,
+            &token_program,
+            false,
+            0,
+        );
+
+        assert!(assert_mint_token_program(&mint_info, &program_info).is_ok());
+        mint_info.owner = &token_2022;
+        assert_eq!(
+            assert_mint_token_program(&mint_info, &program_info).unwrap_err(),
+            ErrorCode::MintMismatch.into()
+        );
+    }
+}
+
+mod count_against_throttle_test {
+    use crate::{utils::count_against_throttle, CandyMachine, ErrorCode};
+
+    #[test]
+    fn count_against_throttle_test() {
+        let mut candy_machine = CandyMachine {
+            max_per_slot: Some(2),
+            ..CandyMachine::default()
+        };
+
+        count_against_throttle(&mut candy_machine, 10).unwrap();
+        count_against_throttle(&mut candy_machine, 10).unwrap();
+        assert_eq!(
+            count_against_throttle(&mut candy_machine, 10).unwrap_err(),
+            ErrorCode::MintingTooFast.into()
+        );
+
+        count_against_throttle(&mut candy_machine, 11).unwrap();
+        assert_eq!(candy_machine.last_mint_slot, 11);
+        assert_eq!(candy_machine.mints_this_slot, 1);
+    }
+
+    #[test]
+    fn unthrottled_test() {
+        let mut candy_machine = CandyMachine::default();
+        for _ in 0..5 {
+            count_against_throttle(&mut candy_machine, 10).unwrap();
+        }
+        assert_eq!(candy_machine.mints_this_slot, 0);
+    }
+}
+
+mod is_live_test {
+    use crate::utils::is_live;
+
+    #[test]
+    fn is_live_test() {
+        let cases = &[
+            // (go live date, now, is live)
+            (None, 100, false),
+            (Some(50), 49, false),
+            (Some(50), 50, true),
+            (Some(0), 100, true),
+            // a zeroed clock is never live
+            (Some(0), 0, false),
+            (Some(-10), -5, false),
+        ];
+        for (go_live_date, now, expected) in cases {
+            assert_eq!(
+                is_live(*go_live_date, *now),
+                *expected,
+                "go_live_date: {:?}, now: {}",
+                go_live_date,
+                now
+            );
+        }
+    }
+}
+
+mod next_mint_index_test {
+    use crate::{test_utils::make_candy_machine_data, utils::next_mint_index, CandyMachine};
+
+    #[test]
+    fn next_mint_index_test() {
+        let cases = &[
+            // (items redeemed, next index)
+            (0, Some(0)),
+            (3, Some(3)),
+            (9, Some(9)),
+            (10, None),
+        ];
+        for (items_redeemed, next_index) in cases {
+            let cm = CandyMachine {
+                data: make_candy_machine_data(5, 10),
+                items_redeemed: *items_redeemed,
+                ..CandyMachine::default()
+            };
+            assert_eq!(
+                next_mint_index(&cm),
+                *next_index,
+                "items_redeemed: {}",
+                items_redeemed
+            );
+        }
+    }
+}
+
+mod active_priority_collection_test {
+    use {
+        crate::{utils::active_priority_collection, CandyMachine, CandyMachineData, MintOptions},
+        anchor_lang::prelude::Pubkey,
+    };
+
+    fn candy_machine(
+        public_date: Option<i64>,
+        priority_collection: Option<Pubkey>,
+    ) -> CandyMachine {
+        CandyMachine {
+            data: CandyMachineData {
+                go_live_date: Some(50),
+                ..CandyMachineData::default()
+            },
+            mint_options: MintOptions {
+                public_date,
+                priority_collection,
+                ..MintOptions::default()
+            },
+            ..CandyMachine::default()
+        }
+    }
+
+    #[test]
+    fn active_priority_collection_test() {
+        let collection = Pubkey::new_unique();
+        let cases = &[
+            // (public date, priority collection, now, expected)
+            (Some(100), Some(collection), 50, Some(collection)),
+            (Some(100), Some(collection), 99, Some(collection)),
+            (Some(100), Some(collection), 100, None),
+            (None, Some(collection), 1000, Some(collection)),
+            (Some(100), None, 50, None),
+            (None, None, 50, None),
+        ];
+        for (public_date, priority_collection, now, expected) in cases {
+            let result = active_priority_collection(
+                &candy_machine(*public_date, *priority_collection),
+                *now,
+            );
+            assert_eq!(
+                result, *expected,
+                "public_date: {:?}, now: {}",
+                public_date, now
+            );
+        }
+    }
+}
+
+mod payment_vault_test {
+    use {
+        crate::{utils::assert_payment_vault, ErrorCode, PAYMENT_VAULT, PREFIX},
+        anchor_lang::prelude::Pubkey,
+    };
+
+    #[test]
+    fn payment_vault_test() {
+        let payer = Pubkey::new_unique();
+        let (vault, bump) = Pubkey::find_program_address(
+            &[PREFIX.as_bytes(), PAYMENT_VAULT.as_bytes(), payer.as_ref()],
+            &crate::id(),
+        );
+
+        assert!(assert_payment_vault(&vault, &payer, bump).is_ok());
+        assert_eq!(
+            assert_payment_vault(&vault, &Pubkey::new_unique(), bump).unwrap_err(),
+            ErrorCode::PaymentVaultMismatch.into()
+        );
+    }
+}
+
+mod count_wallet_mint_test {
+    use crate::{utils::count_wallet_mint, ErrorCode, RateLimit, WalletMints};
+
+    const NOW: i64 = 1_640_000_000;
+
+    #[test]
+    fn count_wallet_mint_test() {
+        let rate_limit = RateLimit {
+            max_mints: 1,
+            window_seconds: 86400,
+        };
+        let mut wallet_mints = WalletMints::default();
+
+        count_wallet_mint(&mut wallet_mints, Some(&rate_limit), None, NOW).unwrap();
+        assert_eq!(wallet_mints.window_start, NOW);
+        assert_eq!(wallet_mints.minted, 1);
+
+        assert_eq!(
+            count_wallet_mint(&mut wallet_mints, Some(&rate_limit), None, NOW + 86399).unwrap_err(),
+            ErrorCode::WalletMintLimitReached.into()
+        );
+        assert_eq!(wallet_mints.minted, 1);
+
+        count_wallet_mint(&mut wallet_mints, Some(&rate_limit), None, NOW + 86400).unwrap();
+        assert_eq!(wallet_mints.window_start, NOW + 86400);
+        assert_eq!(wallet_mints.minted, 1);
+        assert_eq!(wallet_mints.total_minted, 2);
+    }
+
+    #[test]
+    fn pass_allocation_test() {
+        let mut wallet_mints = WalletMints::default();
+
+        for _ in 0..3 {
+            count_wallet_mint(&mut wallet_mints, None, Some(3), NOW).unwrap();
+        }
+        assert_eq!(
+            count_wallet_mint(&mut wallet_mints, None, Some(3), NOW).unwrap_err(),
+            ErrorCode::PassAllocationReached.into()
+        );
+        // passes bought later raise the allocation
+        count_wallet_mint(&mut wallet_mints, None, Some(4), NOW).unwrap();
+        assert_eq!(wallet_mints.total_minted, 4);
+        assert_eq!(wallet_mints.minted, 0);
+    }
+}
+
+mod proof_of_work_test {
+    use {
+        crate::{utils::assert_proof_of_work, ErrorCode},
+        anchor_lang::prelude::Pubkey,
+    };
+
+    #[test]
+    fn proof_of_work_test() {
+        let candy_machine = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let check = |nonce: u64, difficulty: u8| {
+            assert_proof_of_work(&candy_machine, &payer, nonce, difficulty)
+        };
+
+        let valid = (0..).find(|nonce| check(*nonce, 8).is_ok()).unwrap();
+        assert!(check(valid, 0).is_ok());
+        assert_eq!(
+            check(valid, 255).unwrap_err(),
+            ErrorCode::InvalidProofOfWork.into()
+        );
+
+        let invalid = (0..).find(|nonce| check(*nonce, 8).is_err()).unwrap();
+        assert_eq!(
+            check(invalid, 8).unwrap_err(),
+            ErrorCode::InvalidProofOfWork.into()
+        );
+    }
+}
+
+mod ed25519_authorization_test {
+    use {
+        crate::{
+            utils::{assert_ed25519_authorization, assert_mint_authorization, ed25519_program},
+            ErrorCode, MintAuthorization,
+        },
+        anchor_lang::{
+            prelude::{AccountInfo, Pubkey},
+            solana_program::instruction::Instruction,
+        },
+    };
+
+    // laid out like the ed25519 program expects, with a zeroed signature it never checks here
+    fn instruction(public_key: &Pubkey, message: &[u8], instruction_index: u16) -> Instruction {
+        let mut data = vec![1, 0];
+        for offset in [
+            48,
+            u16::MAX,
+            16,
+            instruction_index,
+            112,
+            message.len() as u16,
+        ] {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data.extend_from_slice(&instruction_index.to_le_bytes());
+        data.extend_from_slice(public_key.as_ref());
+        data.extend_from_slice(&[0; 64]);
+        data.extend_from_slice(message);
+        Instruction {
+            program_id: ed25519_program::id(),
+            accounts: vec![],
+            data,
+        }
+    }
+
+    #[test]
+    fn ed25519_authorization_test() {
+        let authority = Pubkey::new_unique();
+        let message = [1; 64];
+        let check = |instruction: Instruction| {
+            assert_ed25519_authorization(&instruction, &authority, &message)
+        };
+
+        assert!(check(instruction(&authority, &message, u16::MAX)).is_ok());
+        assert_eq!(
+            check(instruction(&Pubkey::new_unique(), &message, u16::MAX)).unwrap_err(),
+            ErrorCode::InvalidMintAuthorization.into()
+        );
+        assert_eq!(
+            check(instruction(&authority, &[2; 64], u16::MAX)).unwrap_err(),
+            ErrorCode::InvalidMintAuthorization.into()
+        );
+        // the key and message the program verified could then live in another instruction
+        assert_eq!(
+            check(instruction(&authority, &message, 0)).unwrap_err(),
+            ErrorCode::InvalidMintAuthorization.into()
+        );
+
+        let mut other_program = instruction(&authority, &message, u16::MAX);
+        other_program.program_id = Pubkey::new_unique();
+        assert_eq!(
+            check(other_program).unwrap_err(),
+            ErrorCode::InvalidMintAuthorization.into()
+        );
+    }
+
+    #[test]
+    fn mint_authorization_expiry_test() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [];
+        let not_the_sysvar =
+            AccountInfo::new(&key, false, false, &mut lamports, &mut data, &key, false, 0);
+        let authorization = MintAuthorization {
+            nonce: 7,
+            expires_at: 100,
+        };
+        let check =
+            |now| assert_mint_authorization(&not_the_sysvar, &key, &key, &key, &authorization, now);
+
+        assert_eq!(
+            check(101).unwrap_err(),
+            ErrorCode::MintAuthorizationExpired.into()
+        );
+        // still valid at its expiry, so it fails on the account instead
+        assert_eq!(
+            check(100).unwrap_err(),
+            ErrorCode::InvalidMintAuthorization.into()
+        );
+    }
+}
+
+mod get_config_line_test {
+    use {
+        crate::test_utils::{make_config_account_data, make_config_data},
+        crate::{get_config_line, ErrorCode},
+        anchor_lang::prelude::{AccountInfo, ProgramError, Pubkey},
+    };
+
+    fn read_line(account_data: &mut [u8], index: usize) -> Result<String, ProgramError> {
+        let key = Pubkey::default();
+        let owner = crate::id();
+        let mut lamports = 0;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            account_data,
+            &owner,
+            false,
+            0,
+        );
+        get_config_line(&info, index).map(|line| line.name)
+    }
+
+    #[test]
+    fn reads_uploaded_lines_test() {
+        let mut account_data =
+            make_config_account_data(make_config_data(4), &[("First", "uri"), ("Second", "uri")]);
+        let name = read_line(&mut account_data, 1).unwrap();
+        assert_eq!(name.trim_end_matches(char::from(0)), "Second");
+    }
+
+    #[test]
+    fn trims_padding_test() {
+        let mut account_data = make_config_account_data(make_config_data(4), &[("Short", "uri")]);
+        let name = read_line(&mut account_data, 0).unwrap();
+        assert_eq!(name, "Short");
+    }
+
+    #[test]
+    fn index_out_of_range_test() {
+        let mut account_data =
+            make_config_account_data(make_config_data(4), &[("First", "uri"), ("Second", "uri")]);
+        for index in &[2, 3, usize::MAX / 2, usize::MAX] {
+            let result = read_line(&mut account_data, *index);
+            assert_eq!(
+                result.unwrap_err(),
+                ErrorCode::IndexGreaterThanLength.into(),
+                "index: {}",
+                index
+            );
+        }
+    }
+
+    #[test]
+    fn corrupt_count_test() {
+        let mut account_data = make_config_account_data(make_config_data(4), &[("First", "uri")]);
+        let count_start = crate::CONFIG_ARRAY_START;
+        account_data[count_start..count_start + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let result = read_line(&mut account_data, u32::MAX as usize - 1);
+        assert_eq!(
+            result.unwrap_err(),
+            ErrorCode::IndexGreaterThanLength.into()
+        );
+    }
+}
+
+mod config_progress_test {
+    use {
+        crate::{
+            config_progress,
+            test_utils::{make_config_account_data, make_config_data},
+        },
+        anchor_lang::prelude::{AccountInfo, Pubkey},
+    };
+
+    #[test]
+    fn partially_filled_test() {
+        let mut account_data =
+            make_config_account_data(make_config_data(10), &[("First", "uri"), ("Second", "uri")]);
+        let key = Pubkey::default();
+        let owner = crate::id();
+        let mut lamports = 0;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut account_data,
+            &owner,
+            false,
+            0,
+        );
+        assert_eq!(config_progress(&info).unwrap(), (2, 10));
+    }
+}
+
+mod verify_config_upload_test {
+    use {
+        crate::{
+            test_utils::{make_config_account_data, make_config_data},
+            verify_config_upload, ErrorCode,
+        },
+        anchor_lang::prelude::{AccountInfo, ProgramResult, Pubkey},
+    };
+
+    fn verify(lines: &[(&str, &str)], expected: u32) -> ProgramResult {
+        let mut account_data = make_config_account_data(make_config_data(10), lines);
+        let key = Pubkey::default();
+        let owner = crate::id();
+        let mut lamports = 0;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut account_data,
+            &owner,
+            false,
+            0,
+        );
+        verify_config_upload(&info, expected)
+    }
+
+    #[test]
+    fn full_upload_test() {
+        let lines = [("First", "uri"), ("Second", "uri"), ("Third", "uri")];
+        assert!(verify(&lines, 3).is_ok());
+        assert_eq!(
+            verify(&lines, 4).unwrap_err(),
+            ErrorCode::ConfigIncomplete.into()
+        );
+    }
+
+    #[test]
+    fn blank_line_test() {
+        let lines = [("First", "uri"), ("", ""), ("Third", "uri")];
+        assert_eq!(
+            verify(&lines, 3).unwrap_err(),
+            ErrorCode::ConfigIncomplete.into()
+        );
+    }
+}
+
+mod assert_lines_uploaded_test {
+    use crate::{
+        assert_lines_uploaded, config_line_size,
+        test_utils::{make_config_account_data, make_config_data},
+        ErrorCode, CONFIG_ARRAY_START,
+    };
+
+    #[test]
+    fn full_upload_test() {
+        let data = make_config_data(16);
+        let lines = [("Line", "uri"); 9];
+        let account_data = make_config_account_data(data.clone(), &lines);
+
+        assert!(assert_lines_uploaded(&account_data, &data, 8).is_ok());
+        assert!(assert_lines_uploaded(&account_data, &data, 9).is_ok());
+        assert_eq!(
+            assert_lines_uploaded(&account_data, &data, 10).unwrap_err(),
+            ErrorCode::ConfigIncomplete.into()
+        );
+    }
+
+    #[test]
+    fn gap_test() {
+        let data = make_config_data(16);
+        let lines = [("Line", "uri"); 9];
+        let mut account_data = make_config_account_data(data.clone(), &lines);
+        // line 3 never uploaded
+        let bit_mask_start = CONFIG_ARRAY_START + 4 + 16 * config_line_size(&data) + 4;
+        account_data[bit_mask_start] &= !(1 << 4);
+
+        assert!(assert_lines_uploaded(&account_data, &data, 3).is_ok());
+        assert_eq!(
+            assert_lines_uploaded(&account_data, &data, 4).unwrap_err(),
+            ErrorCode::ConfigIncomplete.into()
+        );
+    }
+}
+
+mod export_config_lines_test {
+    use {
+        crate::{
+            export_config_lines,
+            test_utils::{make_config_account_data, make_config_data},
+        },
+        anchor_lang::prelude::{AccountInfo, Pubkey},
+    };
+
+    #[test]
+    fn trims_padding_test() {
+        let mut account_data = make_config_account_data(
+            make_config_data(4),
+            &[("First", "uri-1"), ("Second", "uri-2")],
+        );
+        let key = Pubkey::default();
+        let owner = crate::id();
+        let mut lamports = 0;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut account_data,
+            &owner,
+            false,
+            0,
+        );
+
+        let lines = export_config_lines(&info).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].name, "First");
+        assert_eq!(lines[0].uri, "uri-1");
+        assert_eq!(lines[1].name, "Second");
+        assert_eq!(lines[1].uri, "uri-2");
+        for line in lines {
+            assert!(!line.name.contains('\0') && !line.uri.contains('\0'));
+        }
+    }
+}
+
+mod config_header_getters_test {
+    use {
+        crate::{
+            get_config_data, get_config_is_mutable, get_config_retain_authority,
+            get_config_seller_fee, get_config_symbol,
+            test_utils::{make_config_account_data, make_config_data},
+            Creator,
+        },
+        anchor_lang::prelude::{AccountInfo, Pubkey},
+    };
+
+    #[test]
+    fn reads_header_fields_test() {
+        let mut data = make_config_data(4);
+        data.creators = vec![
+            Creator {
+                address: Pubkey::new_unique(),
+                verified: false,
+                share: 60,
+            },
+            Creator {
+                address: Pubkey::new_unique(),
+                verified: false,
+                share: 40,
+            },
+        ];
+        data.seller_fee_basis_points = 750;
+        data.is_mutable = true;
+        data.retain_authority = false;
+        let mut account_data = make_config_account_data(data, &[("First", "uri")]);
+        let key = Pubkey::default();
+        let owner = crate::id();
+        let mut lamports = 0;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut account_data,
+            &owner,
+            false,
+            0,
+        );
+
+        let config_data = get_config_data(&info).unwrap();
+        assert_eq!(get_config_symbol(&info).unwrap(), "SYMBOL");
+        assert_eq!(
+            get_config_seller_fee(&info).unwrap(),
+            config_data.seller_fee_basis_points
+        );
+        assert_eq!(
+            get_config_is_mutable(&info).unwrap(),
+            config_data.is_mutable
+        );
+        assert_eq!(
+            get_config_retain_authority(&info).unwrap(),
+            config_data.retain_authority
+        );
+    }
+}
+
+mod get_config_creators_test {
+    use {
+        crate::{
+            get_config_creators,
+            test_utils::{make_config_account_data, make_config_data},
+            Creator,
+        },
+        anchor_lang::prelude::{AccountInfo, Pubkey},
+    };
+
+    #[test]
+    fn reads_creators_test() {
+        let creators = vec![
+            Creator {
+                address: Pubkey::new_unique(),
+                verified: false,
+                share: 60,
+            },
+            Creator {
+                address: Pubkey::new_unique(),
+                verified: false,
+                share: 40,
+            },
+        ];
+        let mut data = make_config_data(4);
+        data.creators = creators.clone();
+        let mut account_data = make_config_account_data(data, &[("First", "uri")]);
+        let key = Pubkey::default();
+        let owner = crate::id();
+        let mut lamports = 0;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut account_data,
+            &owner,
+            false,
+            0,
+        );
+
+        let read = get_config_creators(&info).unwrap();
+        assert_eq!(read.len(), 2);
+        for (read, expected) in read.iter().zip(&creators) {
+            assert_eq!(read.address, expected.address);
+            assert_eq!(read.share, expected.share);
+        }
+    }
+}
+
+mod count_uploaded_config_lines_test {
+    use crate::{
+        config_line_size, count_uploaded_config_lines,
+        test_utils::{make_config_account_data, make_config_data},
+        CONFIG_ARRAY_START,
+    };
+
+    #[test]
+    fn count_uploaded_config_lines_test() {
+        let data = make_config_data(4);
+        let mut account_data =
+            make_config_account_data(data.clone(), &[("First", "uri-1"), ("Second", "uri-2")]);
+        // a corrupted count is ignored
+        account_data[CONFIG_ARRAY_START..CONFIG_ARRAY_START + 4]
+            .copy_from_slice(&7u32.to_le_bytes());
+        assert_eq!(
+            count_uploaded_config_lines(&account_data, &data).unwrap(),
+            2
+        );
+
+        // lines after a gap count, padding bits past the last line don't
+        let bit_mask_start = CONFIG_ARRAY_START + 4 + 4 * config_line_size(&data) + 4;
+        account_data[bit_mask_start] |= 0b0001_1111;
+        assert_eq!(
+            count_uploaded_config_lines(&account_data, &data).unwrap(),
+            3
+        );
+        account_data[bit_mask_start] = 0b1010_0000;
+        assert_eq!(
+            count_uploaded_config_lines(&account_data, &data).unwrap(),
+            2
+        );
+
+        let empty = make_config_account_data(data.clone(), &[]);
+        assert_eq!(count_uploaded_config_lines(&empty, &data).unwrap(), 0);
+    }
+}
+
+mod decode_config_line_test {
+    use {
+        crate::test_utils::{make_config_account_data, make_config_data},
+        crate::{decode_config_line, ErrorCode},
+    };
+
+    #[test]
+    fn decodes_plain_buffer_test() {
+        let account_data = make_config_account_data(
+            make_config_data(4),
+            &[("First", "uri-1"), ("Second", "uri-2")],
+        );
+        let line = decode_config_line(&account_data, 0).unwrap();
+        assert_eq!(line.name.trim_end_matches(char::from(0)), "First");
+        assert_eq!(line.uri.trim_end_matches(char::from(0)), "uri-1");
+        assert_eq!(line.symbol, None);
+    }
+
+    #[test]
+    fn decodes_compact_buffer_test() {
+        let mut data = make_config_data(4);
+        data.name_prefix = Some("Item".to_string());
+        let account_data = make_config_account_data(data, &[("", "uri-1"), ("", "uri-2")]);
+        let line = decode_config_line(&account_data, 1).unwrap();
+        assert_eq!(line.name, "Item #2");
+        assert_eq!(line.uri.trim_end_matches(char::from(0)), "uri-2");
+    }
+
+    #[test]
+    fn offsets_generated_name_test() {
+        let mut data = make_config_data(4);
+        data.name_prefix = Some("Item".to_string());
+        data.name_offset = 100;
+        let account_data = make_config_account_data(data, &[("", "uri-1")]);
+        let line = decode_config_line(&account_data, 0).unwrap();
+        assert_eq!(line.name, "Item #101");
+    }
+
+    #[test]
+    fn joins_base_uri_test() {
+        let mut data = make_config_data(4);
+        data.base_uri = Some("https://arweave.net/".to_string());
+        let account_data = make_config_account_data(data, &[("First", "1.json")]);
+        let line = decode_config_line(&account_data, 0).unwrap();
+        assert_eq!(line.uri, "https://arweave.net/1.json");
+        assert_eq!(
+            account_data.len(),
+            crate::CONFIG_ARRAY_START + 4 + 4 * (crate::CONFIG_LINE_SIZE - 200 + 32) + 4 + 1
+        );
+    }
+
+    #[test]
+    fn line_not_uploaded_test() {
+        let mut account_data = make_config_account_data(make_config_data(4), &[("First", "uri")]);
+        // a count of two with only the first line written leaves a gap at index 1
+        let count_start = crate::CONFIG_ARRAY_START;
+        account_data[count_start..count_start + 4].copy_from_slice(&2u32.to_le_bytes());
+        assert_eq!(
+            decode_config_line(&account_data, 1).unwrap_err(),
+            ErrorCode::ConfigLineNotUploaded.into()
+        );
+
+        let account_data = make_config_account_data(make_config_data(4), &[("", "uri")]);
+        assert_eq!(
+            decode_config_line(&account_data, 0).unwrap_err(),
+            ErrorCode::ConfigLineNotUploaded.into()
+        );
+    }
+
+    #[test]
+    fn reads_seller_fee_override_test() {
+        let mut account_data =
+            make_config_account_data(make_config_data(4), &[("First", "uri"), ("Second", "uri")]);
+        assert_eq!(
+            decode_config_line(&account_data, 0)
+                .unwrap()
+                .seller_fee_basis_points,
+            None
+        );
+
+        // the seller fee closes the second line, after its name, uri and symbol
+        let fee_start = crate::CONFIG_ARRAY_START + 4 + 2 * crate::CONFIG_LINE_SIZE - 3;
+        account_data[fee_start] = 1;
+        account_data[fee_start + 1..fee_start + 3].copy_from_slice(&2500u16.to_le_bytes());
+        assert_eq!(
+            decode_config_line(&account_data, 1)
+                .unwrap()
+                .seller_fee_basis_points,
+            Some(2500)
+        );
+    }
+
+    #[test]
+    fn truncated_buffer_test() {
+        let account_data = make_config_account_data(make_config_data(4), &[("First", "uri")]);
+        let result = decode_config_line(&account_data[..crate::CONFIG_ARRAY_START + 4], 0);
+        assert_eq!(
+            result.unwrap_err(),
+            ErrorCode::IndexGreaterThanLength.into()
+        );
+    }
+}
+
+mod candy_machine_len_test {
+    use {
+        crate::{
+            AuthorityPresaleMode, CandyMachine, CandyMachineData, HolderDiscount, MintOptions,
+            RateLimit, MAX_DENYLIST_LENGTH,
+        },
+        anchor_lang::{prelude::Pubkey, AnchorSerialize},
+    };
+
+    #[test]
+    fn len_matches_serialized_size_test() {
+        let key = Pubkey::new_unique();
+        let candy_machine = CandyMachine {
+            authority: key,
+            wallet: key,
+            token_mint: Some(key),
+            config: key,
+            data: CandyMachineData {
+                uuid: "123456".to_string(),
+                price: u64::MAX,
+                items_available: u64::MAX,
+                go_live_date: Some(i64::MAX),
+            },
+            items_redeemed: u64::MAX,
+            bump: u8::MAX,
+            denylist: vec![key; MAX_DENYLIST_LENGTH],
+            assigned_indices: u32::MAX,
+            launchpad: Some(key),
+            relayer: Some(key),
+            relayer_reimbursement: u64::MAX,
+            supply_cap: Some(key),
+            signed_authorization: true,
+            token_decimals: u8::MAX,
+            holder_discount: Some(HolderDiscount {
+                collection: key,
+                discount_basis_points: u16::MAX,
+            }),
+            rate_limit: Some(RateLimit {
+                max_mints: u16::MAX,
+                window_seconds: i64::MAX,
+            }),
+            max_per_slot: Some(u16::MAX),
+            last_mint_slot: u64::MAX,
+            mints_this_slot: u16::MAX,
+            pass_mint: Some(key),
+            total_collected: u64::MAX,
+            authority_presale_mode: AuthorityPresaleMode::Disabled,
+            refund_window: Some(i64::MAX),
+            held_proceeds: u64::MAX,
+            mint_options: MintOptions {
+                burn_to_redeem_creator: Some(key),
+                creator_fund: Some(key),
+                public_date: Some(i64::MAX),
+                priority_collection: Some(key),
+                pow_difficulty: u8::MAX,
+            },
+        };
+        assert_eq!(candy_machine.try_to_vec().unwrap().len(), CandyMachine::LEN);
+    }
+}
+
+mod test_utils_test {
+    use {
+        crate::{
+            decode_config_line,
+            test_utils::{
+                find_candy_machine_address, find_master_edition_address, find_metadata_address,
+                make_candy_machine_data, make_config_account_data, make_config_data,
+            },
+        },
+        anchor_lang::prelude::Pubkey,
+    };
+
+    #[test]
+    fn builders_smoke_test() {
+        let data = make_candy_machine_data(5, 2);
+        assert_eq!(data.price, 5);
+        assert_eq!(data.items_available, 2);
+        assert_eq!(data.uuid.len(), 6);
+
+        let account_data = make_config_account_data(make_config_data(2), &[("First", "uri")]);
+        let line = decode_config_line(&account_data, 0).unwrap();
+        assert_eq!(line.name.trim_end_matches(char::from(0)), "First");
+
+        let config = Pubkey::new_unique();
+        let (candy_machine, bump) = find_candy_machine_address(&config, &data.uuid);
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[
+                    b"candy_machine",
+                    config.as_ref(),
+                    data.uuid.as_bytes(),
+                    &[bump]
+                ],
+                &crate::id(),
+            )
+            .unwrap(),
+            candy_machine
+        );
+
+        let mint = Pubkey::new_unique();
+        assert_ne!(
+            find_metadata_address(&mint).0,
+            find_master_edition_address(&mint).0
+        );
+    }
+}
@@ -1,6 +1,7 @@
 import * as anchor from "@project-serum/anchor";
 
 import assert from "assert";
+import nacl from "tweetnacl";
 
 import { AccountLayout, MintLayout, Token } from "@solana/spl-token";
 import {
@@ -16,9 +17,15 @@ const TOKEN_PROGRAM_ID = new PublicKey(
 const SPL_ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID = new PublicKey(
   "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
 );
+const SPL_MEMO_PROGRAM_ID = new PublicKey(
+  "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"
+);
 const TOKEN_METADATA_PROGRAM_ID = new PublicKey(
   "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
 );
+const ED25519_PROGRAM_ID = new PublicKey(
+  "Ed25519SigVerify111111111111111111111111111"
+);
 function fromUTF8Array(data: number[]) {
   // array of bytes
   let str = "",
@@ -107,6 +114,27 @@ export function createAssociatedTokenAccountInstruction(
     data: Buffer.from([]),
   });
 }
+// Has the ed25519 program verify a signature of `signer` over `message`, with the
+// public key, signature and message all stored in this instruction.
+function createEd25519Instruction(
+  signer: anchor.web3.Keypair,
+  message: Buffer
+): TransactionInstruction {
+  const signature = nacl.sign.detached(message, signer.secretKey);
+  const data = Buffer.alloc(112 + message.length);
+  data.writeUInt8(1, 0); // number of signatures
+  [48, 0xffff, 16, 0xffff, 112, message.length, 0xffff].forEach((value, i) =>
+    data.writeUInt16LE(value, 2 + 2 * i)
+  );
+  signer.publicKey.toBuffer().copy(data, 16);
+  Buffer.from(signature).copy(data, 48);
+  message.copy(data, 112);
+  return new TransactionInstruction({
+    keys: [],
+    programId: ED25519_PROGRAM_ID,
+    data,
+  });
+}
 const configArrayStart =
   32 + // authority
   4 +
@@ -120,10 +148,45 @@ const configArrayStart =
   8 + //max supply
   1 + //is mutable
   1 + // retain authority
-  4; // max number of lines;
-const configLineSize = 4 + 32 + 4 + 200;
+  4 + // max number of lines
+  1 +
+  4 +
+  32 + // optional + u32 len + name prefix
+  1 + // debug compute
+  1 +
+  4 +
+  200 + // optional + u32 len + external url
+  1 +
+  4 +
+  168 + // optional + u32 len + base uri
+  1 + // defer creator verification
+  1 +
+  8 + // optional + withdraw delay
+  8 + // name offset
+  1; // frozen
+const configLineSize = 4 + 32 + 4 + 200 + 1 + 4 + 10 + 1 + 2;
+const compactConfigLineSize = 4 + 200;
+// with a base uri lines only store a 32 byte uri suffix
+const baseUriConfigLineSize = configLineSize - 200 + 32;
 
 const CANDY_MACHINE = "candy_machine";
+const noMintArgs = {
+  tipLamports: null,
+  powNonce: null,
+  mintToAta: false,
+  delegatedPayment: false,
+  memo: null,
+  paymentVaultBump: null,
+  holderDiscount: false,
+  authorization: null,
+};
+const noMintOptions = {
+  burnToRedeemCreator: null,
+  creatorFund: null,
+  publicDate: null,
+  priorityCollection: null,
+  powDifficulty: 0,
+};
 describe("nft-candy-machine", function () {
   // Configure the client to use the local cluster.
   const idl = JSON.parse(
@@ -196,7 +259,9 @@ describe("nft-candy-machine", function () {
   const createConfig = async function (
     that,
     retainAuthority: boolean,
-    size: number
+    size: number,
+    overrides: object = {},
+    maxSupplyGuard: anchor.BN = null
   ): Promise<TransactionInstruction> {
     that.authority = anchor.web3.Keypair.generate();
     that.uuid = anchor.web3.Keypair.generate().publicKey.toBase58().slice(0, 6);
@@ -213,7 +278,16 @@ describe("nft-candy-machine", function () {
         creators: [
           { address: myWallet.publicKey, verified: false, share: 100 },
         ],
+        namePrefix: null,
+        debugCompute: false,
+        externalUrl: null,
+        baseUri: null,
+        deferCreatorVerification: false,
+        withdrawDelay: null,
+        nameOffset: new anchor.BN(0),
+        ...overrides,
       },
+      maxSupplyGuard,
       {
         accounts: {
           config: that.config.publicKey,
@@ -232,6 +306,8 @@ describe("nft-candy-machine", function () {
   ): Promise<TransactionInstruction[]> {
     const sample = {
       uri: "www.aol.com",
+      symbol: null,
+      sellerFeeBasisPoints: null,
       isMutable: true,
     };
     const firstVec = [];
@@ -264,6 +340,19 @@ describe("nft-candy-machine", function () {
     } else return [tx1];
   };
 
+  const getMintEscrow = async function (mint: PublicKey) {
+    return (
+      await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          Buffer.from("mint_escrow"),
+          mint.toBuffer(),
+        ],
+        programId
+      )
+    )[0];
+  };
+
   const getTokenWallet = async function (wallet: PublicKey, mint: PublicKey) {
     return (
       await PublicKey.findProgramAddress(
@@ -273,6 +362,408 @@ describe("nft-candy-machine", function () {
     )[0];
   };
 
+  const mintNft = async function (
+    that,
+    buyer: anchor.web3.Keypair,
+    wallet: PublicKey,
+    extra: {
+      remainingAccounts?: anchor.web3.AccountMeta[];
+      signers?: anchor.web3.Keypair[];
+      instructions?: TransactionInstruction[];
+      updateAuthority?: PublicKey;
+      freezeAuthority?: PublicKey;
+      tipLamports?: anchor.BN;
+      powNonce?: anchor.BN;
+      metadata?: PublicKey;
+      mintToAta?: boolean;
+      delegatedPayment?: boolean;
+      memo?: string;
+      escrowOwner?: PublicKey;
+      paymentVaultBump?: number;
+      holderDiscount?: boolean;
+      authorization?: { nonce: anchor.BN; expiresAt: anchor.BN };
+      refundable?: boolean;
+    } = {}
+  ) {
+    const mint = anchor.web3.Keypair.generate();
+    const token = await getTokenWallet(
+      extra.escrowOwner || buyer.publicKey,
+      mint.publicKey
+    );
+    const metadata = extra.metadata || (await getMetadata(mint.publicKey));
+    const masterEdition = await getMasterEdition(mint.publicKey);
+    const [candyMachine, _] = await getCandyMachine(
+      that.config.publicKey,
+      that.candyMachineUuid
+    );
+    const tx = await program.rpc.mintNft(
+      {
+        tipLamports: extra.tipLamports || null,
+        powNonce: extra.powNonce || null,
+        mintToAta: !!extra.mintToAta,
+        delegatedPayment: !!extra.delegatedPayment,
+        memo: extra.memo || null,
+        paymentVaultBump: extra.paymentVaultBump ?? null,
+        holderDiscount: !!extra.holderDiscount,
+        authorization: extra.authorization || null,
+      },
+      {
+        accounts: {
+          config: that.config.publicKey,
+          candyMachine,
+          payer: buyer.publicKey,
+          wallet,
+          mint: mint.publicKey,
+          metadata,
+          masterEdition,
+          mintAuthority: buyer.publicKey,
+          updateAuthority: extra.updateAuthority || buyer.publicKey,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+        remainingAccounts: [
+          ...(extra.remainingAccounts || []),
+          ...(extra.refundable
+            ? [
+                {
+                  pubkey: await getMintEscrow(mint.publicKey),
+                  isWritable: true,
+                  isSigner: false,
+                },
+              ]
+            : []),
+          ...(extra.escrowOwner
+            ? [{ pubkey: token, isWritable: true, isSigner: false }]
+            : []),
+          ...(extra.mintToAta
+            ? [
+                { pubkey: token, isWritable: true, isSigner: false },
+                {
+                  pubkey: SPL_ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID,
+                  isWritable: false,
+                  isSigner: false,
+                },
+              ]
+            : []),
+          ...(extra.memo
+            ? [
+                {
+                  pubkey: SPL_MEMO_PROGRAM_ID,
+                  isWritable: false,
+                  isSigner: false,
+                },
+              ]
+            : []),
+        ],
+        signers: [mint, buyer, myWallet, ...(extra.signers || [])],
+        instructions: [
+          ...(extra.instructions || []),
+          anchor.web3.SystemProgram.createAccount({
+            fromPubkey: myWallet.publicKey,
+            newAccountPubkey: mint.publicKey,
+            space: MintLayout.span,
+            lamports:
+              await provider.connection.getMinimumBalanceForRentExemption(
+                MintLayout.span
+              ),
+            programId: TOKEN_PROGRAM_ID,
+          }),
+          Token.createInitMintInstruction(
+            TOKEN_PROGRAM_ID,
+            mint.publicKey,
+            0,
+            buyer.publicKey,
+            extra.freezeAuthority || buyer.publicKey
+          ),
+          ...(extra.escrowOwner
+            ? [
+                createAssociatedTokenAccountInstruction(
+                  token,
+                  myWallet.publicKey,
+                  extra.escrowOwner,
+                  mint.publicKey
+                ),
+              ]
+            : []),
+          ...(extra.mintToAta || extra.escrowOwner
+            ? []
+            : [
+                createAssociatedTokenAccountInstruction(
+                  token,
+                  myWallet.publicKey,
+                  buyer.publicKey,
+                  mint.publicKey
+                ),
+                Token.createMintToInstruction(
+                  TOKEN_PROGRAM_ID,
+                  mint.publicKey,
+                  token,
+                  buyer.publicKey,
+                  [],
+                  1
+                ),
+              ]),
+        ],
+      }
+    );
+    return { tx, mint, token, metadata, masterEdition };
+  };
+
+  const forceMint = async function (
+    that,
+    recipient: anchor.web3.Keypair,
+    remainingAccounts: anchor.web3.AccountMeta[] = []
+  ) {
+    const mint = anchor.web3.Keypair.generate();
+    const token = await getTokenWallet(recipient.publicKey, mint.publicKey);
+    const metadata = await getMetadata(mint.publicKey);
+    const masterEdition = await getMasterEdition(mint.publicKey);
+    const [candyMachine, _] = await getCandyMachine(
+      that.config.publicKey,
+      that.candyMachineUuid
+    );
+
+    const tx = await program.rpc.forceMint({
+      accounts: {
+        config: that.config.publicKey,
+        candyMachine,
+        authority: that.authority.publicKey,
+        payer: myWallet.publicKey,
+        recipient: recipient.publicKey,
+        recipientTokenAccount: token,
+        mint: mint.publicKey,
+        metadata,
+        masterEdition,
+        mintAuthority: that.authority.publicKey,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+      remainingAccounts,
+      signers: [mint, that.authority, myWallet],
+      instructions: [
+        anchor.web3.SystemProgram.createAccount({
+          fromPubkey: myWallet.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space: MintLayout.span,
+          lamports:
+            await provider.connection.getMinimumBalanceForRentExemption(
+              MintLayout.span
+            ),
+          programId: TOKEN_PROGRAM_ID,
+        }),
+        Token.createInitMintInstruction(
+          TOKEN_PROGRAM_ID,
+          mint.publicKey,
+          0,
+          that.authority.publicKey,
+          that.authority.publicKey
+        ),
+        createAssociatedTokenAccountInstruction(
+          token,
+          myWallet.publicKey,
+          recipient.publicKey,
+          mint.publicKey
+        ),
+        Token.createMintToInstruction(
+          TOKEN_PROGRAM_ID,
+          mint.publicKey,
+          token,
+          that.authority.publicKey,
+          [],
+          1
+        ),
+      ],
+    });
+    return { tx, mint, token, metadata, masterEdition };
+  };
+
+  const getMetadataData = async function (metadata: PublicKey) {
+    const { data } = await connection.getAccountInfo(metadata);
+    const readString = (start: number, length: number) =>
+      fromUTF8Array([...data.slice(start + 4, start + 4 + length)]).replace(
+        /\0/g,
+        ""
+      );
+    // key + update authority + mint
+    const nameStart = 1 + 32 + 32;
+    const symbolStart = nameStart + 4 + 32;
+    const uriStart = symbolStart + 4 + 10;
+    const sellerFeeStart = uriStart + 4 + 200;
+    // optional + u32 len + (address + verified + share) per creator
+    const creatorsStart = sellerFeeStart + 2;
+    const creators = [];
+    let primarySaleStart = creatorsStart + 1;
+    if (data[creatorsStart] == 1) {
+      const count = data.readUInt32LE(creatorsStart + 1);
+      for (let i = 0; i < count; i++) {
+        const start = creatorsStart + 5 + i * 34;
+        creators.push({
+          address: new PublicKey(data.slice(start, start + 32)),
+          verified: data[start + 32] == 1,
+          share: data[start + 33],
+        });
+      }
+      primarySaleStart = creatorsStart + 5 + count * 34;
+    }
+    return {
+      updateAuthority: new PublicKey(data.slice(1, 33)),
+      name: readString(nameStart, 32),
+      symbol: readString(symbolStart, 10),
+      uri: readString(uriStart, 200),
+      sellerFeeBasisPoints: data.readUInt16LE(sellerFeeStart),
+      creators,
+      primarySaleHappened: data[primarySaleStart] == 1,
+    };
+  };
+
+  const initializeCandyMachine = async function (
+    that,
+    options: {
+      size?: number;
+      retainAuthority?: boolean;
+      configOverrides?: object;
+      lineSize?: number;
+      data?: object;
+      mintOptions?: object;
+      maxPriceGuard?: anchor.BN;
+      maxSupplyGuard?: anchor.BN;
+      wallet?: PublicKey;
+      tokenMint?: PublicKey;
+      uploadedLines?: number[];
+      requireFullConfig?: boolean;
+      instructions?: TransactionInstruction[];
+    } = {}
+  ): Promise<PublicKey> {
+    const size = options.size || 10;
+    const space =
+      configArrayStart +
+      4 +
+      size * (options.lineSize || configLineSize) +
+      4 +
+      Math.ceil(size / 8);
+    that.config = anchor.web3.Keypair.generate();
+    const txInstr = await createConfig(
+      that,
+      !!options.retainAuthority,
+      size,
+      options.configOverrides,
+      options.maxSupplyGuard
+    );
+    const linesInstr = options.uploadedLines
+      ? await Promise.all(
+          options.uploadedLines.map((index) =>
+            program.instruction.addConfigLines(
+              index,
+              [
+                {
+                  name: `Sample ${index}`,
+                  uri: "www.aol.com",
+                  symbol: null,
+                  sellerFeeBasisPoints: null,
+                },
+              ],
+              {
+                accounts: {
+                  config: that.config.publicKey,
+                  authority: that.authority.publicKey,
+                },
+                signers: [that.authority, myWallet],
+              }
+            )
+          )
+        )
+      : await addConfigLines(that, size);
+    that.candyMachineUuid = anchor.web3.Keypair.generate()
+      .publicKey.toBase58()
+      .slice(0, 6);
+    const [candyMachine, bump] = await getCandyMachine(
+      that.config.publicKey,
+      that.candyMachineUuid
+    );
+
+    await program.rpc.initializeCandyMachine(
+      bump,
+      {
+        uuid: that.candyMachineUuid,
+        price: new anchor.BN(1000000000),
+        itemsAvailable: new anchor.BN(size),
+        goLiveDate: null,
+        ...options.data,
+      },
+      { ...noMintOptions, ...options.mintOptions },
+      options.maxPriceGuard || null,
+      !!options.requireFullConfig,
+      {
+        accounts: {
+          candyMachine,
+          wallet: options.wallet || myWallet.publicKey,
+          config: that.config.publicKey,
+          authority: that.authority.publicKey,
+          payer: myWallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        },
+        remainingAccounts: options.tokenMint
+          ? [
+              {
+                pubkey: options.tokenMint,
+                isWritable: false,
+                isSigner: false,
+              },
+            ]
+          : [],
+        signers: [myWallet, that.authority, that.config],
+        instructions: [
+          ...(options.instructions || []),
+          anchor.web3.SystemProgram.createAccount({
+            fromPubkey: myWallet.publicKey,
+            newAccountPubkey: that.config.publicKey,
+            space,
+            lamports:
+              await provider.connection.getMinimumBalanceForRentExemption(
+                space
+              ),
+            programId: programId,
+          }),
+          anchor.web3.SystemProgram.transfer({
+            fromPubkey: myWallet.publicKey,
+            toPubkey: that.authority.publicKey,
+            lamports: 5,
+          }),
+          txInstr,
+          ...linesInstr,
+        ],
+      }
+    );
+    return candyMachine;
+  };
+
+  const captureEvent = async function (
+    name: string,
+    send: () => Promise<any>
+  ): Promise<any> {
+    let listener = null;
+    const event = new Promise((resolve) => {
+      listener = program.addEventListener(name, (e) => resolve(e));
+    });
+    await send();
+    const result = await event;
+    await program.removeEventListener(listener);
+    return result;
+  };
+
+  const fund = (to: PublicKey, lamports: number) =>
+    anchor.web3.SystemProgram.transfer({
+      fromPubkey: myWallet.publicKey,
+      toPubkey: to,
+      lamports,
+    });
+
   describe("sol only", function () {
     beforeEach(async function () {
       const config = await anchor.web3.Keypair.generate();
@@ -296,6 +787,9 @@ describe("nft-candy-machine", function () {
             itemsAvailable: new anchor.BN(10),
             goLiveDate: null,
           },
+          noMintOptions,
+          null,
+          false,
           {
             accounts: {
               candyMachine,
@@ -354,6 +848,52 @@ describe("nft-candy-machine", function () {
       }
     });
 
+    it("ignores an empty batch of lines", async function () {
+      await program.rpc.addConfigLines(0, [], {
+        accounts: {
+          config: this.config.publicKey,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+
+      const config = await connection.getAccountInfo(this.config.publicKey);
+      const amountOfConfigs = new anchor.BN(
+        config.data.slice(configArrayStart, configArrayStart + 4),
+        "le"
+      );
+      assert.equal(amountOfConfigs.toNumber(), 10);
+    });
+
+    it("emits an event for uploaded lines", async function () {
+      const event = await captureEvent("ConfigLinesAdded", () =>
+        program.rpc.addConfigLines(
+          3,
+          [
+            {
+              name: "Replaced",
+              uri: "www.aol.com",
+              symbol: null,
+              sellerFeeBasisPoints: null,
+            },
+          ],
+          {
+            accounts: {
+              config: this.config.publicKey,
+              authority: this.authority.publicKey,
+            },
+            signers: [this.authority],
+          }
+        )
+      );
+
+      assert.ok(event.config.equals(this.config.publicKey));
+      assert.equal(event.index, 3);
+      assert.equal(event.count, 1);
+      // overwriting an uploaded line leaves the total alone
+      assert.equal(event.newTotal, 10);
+    });
+
     it("Is initialized!", async function () {
       // Add your test here.
       const [candyMachine, bump] = await getCandyMachine(
@@ -394,7 +934,7 @@ describe("nft-candy-machine", function () {
           this.candyMachineUuid
         );
         try {
-          const tx = await program.rpc.mintNft({
+          const tx = await program.rpc.mintNft(noMintArgs, {
             accounts: {
               config: this.config.publicKey,
               candyMachine: candyMachine,
@@ -483,7 +1023,7 @@ describe("nft-candy-machine", function () {
       );
 
       try {
-        const tx = await program.rpc.mintNft({
+        const tx = await program.rpc.mintNft(noMintArgs, {
           accounts: {
             config: this.config.publicKey,
             candyMachine,
@@ -502,12 +1042,17 @@ describe("nft-candy-machine", function () {
           },
           signers: [mint, this.authority, myWallet],
           instructions: [
-            program.instruction.updateCandyMachine(null, new anchor.BN(500), {
-              accounts: {
-                candyMachine,
-                authority: this.authority.publicKey,
-              },
-            }),
+            program.instruction.updateCandyMachine(
+              null,
+              new anchor.BN(500),
+              null,
+              {
+                accounts: {
+                  candyMachine,
+                  authority: this.authority.publicKey,
+                },
+              }
+            ),
             anchor.web3.SystemProgram.createAccount({
               fromPubkey: myWallet.publicKey,
               newAccountPubkey: mint.publicKey,
@@ -576,7 +1121,7 @@ describe("nft-candy-machine", function () {
         this.candyMachineUuid
       );
       try {
-        const tx = await program.rpc.mintNft({
+        const tx = await program.rpc.mintNft(noMintArgs, {
           accounts: {
             config: this.config.publicKey,
             candyMachine: candyMachine,
@@ -658,78 +1203,3032 @@ describe("nft-candy-machine", function () {
       // less minting fees...
       assert.ok(walletLamports - newWalletLamports < 15000000);
     });
+
+    it("switches to token payment after launch", async function () {
+      const [candyMachine, _] = await getCandyMachine(
+        this.config.publicKey,
+        this.candyMachineUuid
+      );
+      const tokenMint = await Token.createMint(
+        connection,
+        myWallet,
+        myWallet.publicKey,
+        null,
+        0,
+        TOKEN_PROGRAM_ID
+      );
+      const walletToken = await tokenMint.createAssociatedTokenAccount(
+        myWallet.publicKey
+      );
+
+      await program.rpc.setTokenMint({
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+          wallet: walletToken,
+        },
+        remainingAccounts: [
+          { pubkey: tokenMint.publicKey, isWritable: false, isSigner: false },
+        ],
+        signers: [this.authority],
+      });
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.ok(machine.tokenMint.equals(tokenMint.publicKey));
+      assert.ok(machine.wallet.equals(walletToken));
+
+      const payingToken = await tokenMint.createAssociatedTokenAccount(
+        this.authority.publicKey
+      );
+      await tokenMint.mintTo(payingToken, myWallet, [], 1000000000);
+      const transferAuthority = anchor.web3.Keypair.generate();
+      await tokenMint.approve(
+        payingToken,
+        transferAuthority.publicKey,
+        this.authority,
+        [],
+        1000000000
+      );
+
+      await mintNft(this, this.authority, walletToken, {
+        remainingAccounts: [
+          { pubkey: payingToken, isWritable: true, isSigner: false },
+          {
+            pubkey: transferAuthority.publicKey,
+            isWritable: false,
+            isSigner: true,
+          },
+        ],
+        signers: [transferAuthority],
+        instructions: [fund(this.authority.publicKey, 10000000)],
+      });
+
+      const walletBalance = await connection.getTokenAccountBalance(
+        walletToken
+      );
+      assert.equal(walletBalance.value.amount, "1000000000");
+    });
+
+    it("refuses to go back to SOL with a token account wallet", async function () {
+      const [candyMachine, _] = await getCandyMachine(
+        this.config.publicKey,
+        this.candyMachineUuid
+      );
+      const tokenMint = await Token.createMint(
+        connection,
+        myWallet,
+        myWallet.publicKey,
+        null,
+        0,
+        TOKEN_PROGRAM_ID
+      );
+      const walletToken = await tokenMint.createAssociatedTokenAccount(
+        myWallet.publicKey
+      );
+
+      try {
+        await program.rpc.setTokenMint({
+          accounts: {
+            candyMachine,
+            authority: this.authority.publicKey,
+            wallet: walletToken,
+          },
+          signers: [this.authority],
+        });
+        assert.fail("set a token account as the wallet of SOL payments");
+      } catch (e) {
+        assert.equal(e.msg, "Wallet of SOL payments must be a system account");
+      }
+    });
+
+    it("force mints to a third wallet for free before go live", async function () {
+      const recipient = anchor.web3.Keypair.generate();
+      const authorityLamports = await connection.getBalance(
+        this.authority.publicKey
+      );
+      const { metadata, token } = await forceMint(this, recipient);
+      const [candyMachine, _] = await getCandyMachine(
+        this.config.publicKey,
+        this.candyMachineUuid
+      );
+
+      const metadataAccount = await connection.getAccountInfo(metadata);
+      assert.ok(metadataAccount.data.length > 0);
+      const tokenBalance = await connection.getTokenAccountBalance(token);
+      assert.equal(tokenBalance.value.uiAmount, 1);
+      // the authority paid neither the price nor the fees
+      assert.equal(
+        await connection.getBalance(this.authority.publicKey),
+        authorityLamports
+      );
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.equal(machine.itemsRedeemed.toNumber(), 1);
+    });
   });
 
-  describe("token", function () {
-    beforeEach(async function () {
-      const config = await anchor.web3.Keypair.generate();
-      this.config = config;
-      const txInstr = await createConfig(this, true, 5);
-      const linesInstr = await addConfigLines(this, 5);
-      this.tokenMint = anchor.web3.Keypair.generate();
-      this.candyMachineUuid = anchor.web3.Keypair.generate()
+  describe("shared config", function () {
+    it("backs two candy machines that mint independently", async function () {
+      const first = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      const firstUuid = this.candyMachineUuid;
+
+      const secondUuid = anchor.web3.Keypair.generate()
         .publicKey.toBase58()
         .slice(0, 6);
-      const [candyMachine, bump] = await getCandyMachine(
+      const [second, bump] = await getCandyMachine(
         this.config.publicKey,
-        this.candyMachineUuid
+        secondUuid
       );
-      this.walletToken = await getTokenWallet(
-        myWallet.publicKey,
-        this.tokenMint.publicKey
+      await program.rpc.initializeCandyMachine(
+        bump,
+        {
+          uuid: secondUuid,
+          price: new anchor.BN(0),
+          itemsAvailable: new anchor.BN(5),
+          goLiveDate: null,
+        },
+        noMintOptions,
+        null,
+        false,
+        {
+          accounts: {
+            candyMachine: second,
+            wallet: myWallet.publicKey,
+            config: this.config.publicKey,
+            authority: this.authority.publicKey,
+            payer: myWallet.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          },
+          signers: [myWallet, this.authority],
+        }
+      );
+
+      for (const uuid of [firstUuid, secondUuid]) {
+        this.candyMachineUuid = uuid;
+        const { metadata } = await mintNft(
+          this,
+          this.authority,
+          myWallet.publicKey,
+          { instructions: [fund(this.authority.publicKey, 10000000)] }
+        );
+        assert.equal((await getMetadataData(metadata)).name, "Sample 0");
+      }
+      for (const candyMachine of [first, second]) {
+        const machine: CandyMachine = await program.account.candyMachine.fetch(
+          candyMachine
+        );
+        assert.equal(machine.itemsRedeemed.toNumber(), 1);
+      }
+    });
+
+    it("can't be used by another authority", async function () {
+      await initializeCandyMachine(this, { size: 5 });
+      const stranger = anchor.web3.Keypair.generate();
+      const uuid = anchor.web3.Keypair.generate()
+        .publicKey.toBase58()
+        .slice(0, 6);
+      const [candyMachine, bump] = await getCandyMachine(
+        this.config.publicKey,
+        uuid
       );
+      let failed = false;
       try {
-        const tx = await program.rpc.initializeCandyMachine(
+        await program.rpc.initializeCandyMachine(
           bump,
           {
-            uuid: this.candyMachineUuid,
-            price: new anchor.BN(1),
+            uuid,
+            price: new anchor.BN(0),
             itemsAvailable: new anchor.BN(5),
             goLiveDate: null,
           },
+          noMintOptions,
+          null,
+          false,
           {
             accounts: {
               candyMachine,
-              wallet: this.walletToken,
+              wallet: myWallet.publicKey,
               config: this.config.publicKey,
-              authority: this.authority.publicKey,
+              authority: stranger.publicKey,
               payer: myWallet.publicKey,
               systemProgram: anchor.web3.SystemProgram.programId,
               rent: anchor.web3.SYSVAR_RENT_PUBKEY,
             },
-            remainingAccounts: [
-              {
-                pubkey: this.tokenMint.publicKey,
-                isWritable: false,
-                isSigner: true,
-              },
-            ],
-            signers: [myWallet, this.tokenMint, this.authority, this.config],
-            instructions: [
-              anchor.web3.SystemProgram.createAccount({
-                fromPubkey: myWallet.publicKey,
-                newAccountPubkey: config.publicKey,
-                space: configArrayStart + 4 + 5 * configLineSize + 4 + 1,
-                lamports:
-                  await provider.connection.getMinimumBalanceForRentExemption(
-                    configArrayStart + 4 + 5 * configLineSize + 4 + 1
-                  ),
-                programId: programId,
-              }),
-              anchor.web3.SystemProgram.transfer({
-                fromPubkey: myWallet.publicKey,
-                toPubkey: this.authority.publicKey,
-                lamports: 5,
-              }),
-              anchor.web3.SystemProgram.createAccount({
-                fromPubkey: myWallet.publicKey,
-                newAccountPubkey: this.tokenMint.publicKey,
-                space: MintLayout.span,
-                lamports:
-                  await provider.connection.getMinimumBalanceForRentExemption(
-                    MintLayout.span
-                  ),
-                programId: TOKEN_PROGRAM_ID,
+            signers: [myWallet, stranger],
+            instructions: [fund(stranger.publicKey, 1000000)],
+          }
+        );
+      } catch (e) {
+        failed = true;
+      }
+      assert.ok(failed, "initialized from another authority's config");
+    });
+  });
+
+  describe("update authority", function () {
+    it("must sign when authority is not retained", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      try {
+        await mintNft(this, this.authority, myWallet.publicKey, {
+          updateAuthority: anchor.web3.Keypair.generate().publicKey,
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        });
+        assert.fail("minted without the update authority signing");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Update authority must sign when authority is not retained"
+        );
+      }
+    });
+
+    describe("retained", function () {
+      beforeEach(async function () {
+        await initializeCandyMachine(this, {
+          size: 5,
+          retainAuthority: true,
+          data: { price: new anchor.BN(0), goLiveDate: new anchor.BN(0) },
+        });
+        this.buyer = anchor.web3.Keypair.generate();
+      });
+
+      it("does not need the candy machine authority to sign", async function () {
+        const { metadata } = await mintNft(
+          this,
+          this.buyer,
+          myWallet.publicKey,
+          {
+            updateAuthority: this.authority.publicKey,
+            instructions: [fund(this.buyer.publicKey, 10000000)],
+          }
+        );
+        const { updateAuthority } = await getMetadataData(metadata);
+        assert.ok(updateAuthority.equals(this.authority.publicKey));
+      });
+
+      it("rejects any other update authority", async function () {
+        try {
+          await mintNft(this, this.buyer, myWallet.publicKey, {
+            instructions: [fund(this.buyer.publicKey, 10000000)],
+          });
+          assert.fail("minted with the buyer as update authority");
+        } catch (e) {
+          assert.equal(
+            e.msg,
+            "Update authority must be the candy machine authority when authority is retained"
+          );
+        }
+      });
+    });
+  });
+
+  describe("config authority", function () {
+    it("rejects a candy machine on another authority's config", async function () {
+      await initializeCandyMachine(this, { size: 5 });
+      const outsider = anchor.web3.Keypair.generate();
+      const uuid = anchor.web3.Keypair.generate()
+        .publicKey.toBase58()
+        .slice(0, 6);
+      const [candyMachine, bump] = await getCandyMachine(
+        this.config.publicKey,
+        uuid
+      );
+      try {
+        await program.rpc.initializeCandyMachine(
+          bump,
+          {
+            uuid,
+            price: new anchor.BN(0),
+            itemsAvailable: new anchor.BN(5),
+            goLiveDate: null,
+          },
+          noMintOptions,
+          null,
+          false,
+          {
+            accounts: {
+              candyMachine,
+              wallet: myWallet.publicKey,
+              config: this.config.publicKey,
+              authority: outsider.publicKey,
+              payer: myWallet.publicKey,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+            signers: [myWallet, outsider],
+            instructions: [fund(outsider.publicKey, 10000000)],
+          }
+        );
+        assert.fail("created a candy machine on another authority's config");
+      } catch (e) {
+        assert.equal(e.msg, "A has one constraint was violated");
+      }
+    });
+
+    it("can be rotated to a new key that then adds lines", async function () {
+      await initializeCandyMachine(this, { size: 5 });
+      const newAuthority = anchor.web3.Keypair.generate();
+      await program.rpc.setConfigAuthority(newAuthority.publicKey, {
+        accounts: {
+          config: this.config.publicKey,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+      const config: Config = await program.account.config.fetch(
+        this.config.publicKey
+      );
+      assert.ok(config.authority.equals(newAuthority.publicKey));
+
+      const line = {
+        name: "Rotated",
+        uri: "www.aol.com",
+        symbol: null,
+        sellerFeeBasisPoints: null,
+      };
+      let failed = false;
+      try {
+        await program.rpc.addConfigLines(0, [line], {
+          accounts: {
+            config: this.config.publicKey,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        });
+      } catch (e) {
+        failed = true;
+      }
+      assert.ok(failed, "the old authority still added lines");
+
+      await program.rpc.addConfigLines(0, [line], {
+        accounts: {
+          config: this.config.publicKey,
+          authority: newAuthority.publicKey,
+        },
+        signers: [newAuthority],
+      });
+    });
+  });
+
+  describe("refund", function () {
+    it("returns a buyer's payment from the wallet", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      const buyer = anchor.web3.Keypair.generate();
+      await mintNft(this, buyer, myWallet.publicKey, {
+        instructions: [fund(buyer.publicKey, 1000000000 + 10000000)],
+      });
+      const paidBalance = await connection.getBalance(buyer.publicKey);
+
+      const event = await captureEvent("RefundEvent", () =>
+        program.rpc.refund(new anchor.BN(1000000000), {
+          accounts: {
+            candyMachine,
+            authority: this.authority.publicKey,
+            wallet: myWallet.publicKey,
+            buyer: buyer.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [this.authority, myWallet],
+        })
+      );
+
+      assert.ok(event.buyer.equals(buyer.publicKey));
+      assert.equal(event.amount.toNumber(), 1000000000);
+      assert.equal(
+        await connection.getBalance(buyer.publicKey),
+        paidBalance + 1000000000
+      );
+    });
+  });
+
+  describe("go live", function () {
+    const mintAsBuyer = async function (that) {
+      const buyer = anchor.web3.Keypair.generate();
+      await mintNft(that, buyer, myWallet.publicKey, {
+        instructions: [fund(buyer.publicKey, 10000000)],
+      });
+    };
+
+    it("tells a missing go live date apart", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0), goLiveDate: null },
+      });
+
+      try {
+        await mintAsBuyer(this);
+        assert.fail("minted without a go live date");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Candy machine has no go live date, only its authority can mint"
+        );
+      }
+    });
+
+    it("tells a scheduled go live date apart", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: {
+          price: new anchor.BN(0),
+          goLiveDate: new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        },
+      });
+
+      try {
+        await mintAsBuyer(this);
+        assert.fail("minted before the go live date");
+      } catch (e) {
+        assert.equal(e.msg, "Candy machine is not live yet!");
+      }
+    });
+  });
+
+  describe("denylist", function () {
+    beforeEach(async function () {
+      this.candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0), goLiveDate: new anchor.BN(0) },
+      });
+      this.buyer = anchor.web3.Keypair.generate();
+    });
+
+    it("blocks a denied payer until removed", async function () {
+      const accounts = {
+        candyMachine: this.candyMachine,
+        authority: this.authority.publicKey,
+      };
+      await program.rpc.addToDenylist(this.buyer.publicKey, {
+        accounts,
+        signers: [this.authority],
+      });
+
+      try {
+        await mintNft(this, this.buyer, myWallet.publicKey, {
+          instructions: [fund(this.buyer.publicKey, 10000000)],
+        });
+        assert.fail("denied payer minted");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "This address is not allowed to mint from this candy machine"
+        );
+      }
+
+      await program.rpc.removeFromDenylist(this.buyer.publicKey, {
+        accounts,
+        signers: [this.authority],
+      });
+      const { metadata } = await mintNft(
+        this,
+        this.buyer,
+        myWallet.publicKey,
+        { instructions: [fund(this.buyer.publicKey, 10000000)] }
+      );
+      assert.ok((await connection.getAccountInfo(metadata)).data.length > 0);
+    });
+  });
+
+  describe("compute logging", function () {
+    const mintLogs = async function (that, debugCompute: boolean) {
+      await initializeCandyMachine(that, {
+        size: 5,
+        configOverrides: { debugCompute },
+        data: { price: new anchor.BN(0) },
+      });
+      const { tx } = await mintNft(that, that.authority, myWallet.publicKey, {
+        instructions: [fund(that.authority.publicKey, 10000000)],
+      });
+      await connection.confirmTransaction(tx, "confirmed");
+      const { meta } = await connection.getTransaction(tx, {
+        commitment: "confirmed",
+      });
+      return meta.logMessages.join("\n");
+    };
+
+    it("logs compute units only when enabled", async function () {
+      const enabled = await mintLogs(this, true);
+      assert.ok(enabled.includes("after create_master_edition"));
+      assert.ok(enabled.includes("units remaining"));
+
+      const disabled = await mintLogs(this, false);
+      assert.ok(!disabled.includes("after create_master_edition"));
+    });
+  });
+
+  describe("token wallet", function () {
+    it("rejects a frozen wallet token account", async function () {
+      const tokenMint = await Token.createMint(
+        connection,
+        myWallet,
+        myWallet.publicKey,
+        myWallet.publicKey,
+        0,
+        TOKEN_PROGRAM_ID
+      );
+      const walletToken = await tokenMint.createAssociatedTokenAccount(
+        myWallet.publicKey
+      );
+      await tokenMint.freezeAccount(walletToken, myWallet, []);
+
+      try {
+        await initializeCandyMachine(this, {
+          size: 5,
+          wallet: walletToken,
+          tokenMint: tokenMint.publicKey,
+        });
+        assert.fail("initialized with a frozen wallet");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Wallet token account is frozen and can't receive payments"
+        );
+      }
+    });
+  });
+
+  describe("token decimals", function () {
+    it("charges a 6 decimal token price in raw units", async function () {
+      const tokenMint = await Token.createMint(
+        connection,
+        myWallet,
+        myWallet.publicKey,
+        null,
+        6,
+        TOKEN_PROGRAM_ID
+      );
+      const walletToken = await tokenMint.createAssociatedTokenAccount(
+        myWallet.publicKey
+      );
+      // 1.5 tokens
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        wallet: walletToken,
+        tokenMint: tokenMint.publicKey,
+        data: { price: new anchor.BN(1500000) },
+      });
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.equal(machine.tokenDecimals, 6);
+
+      const transferAuthority = anchor.web3.Keypair.generate();
+      const payingToken = await tokenMint.createAssociatedTokenAccount(
+        this.authority.publicKey
+      );
+      await tokenMint.mintTo(payingToken, myWallet, [], 2000000);
+      await tokenMint.approve(
+        payingToken,
+        transferAuthority.publicKey,
+        this.authority,
+        [],
+        1500000
+      );
+      await mintNft(this, this.authority, walletToken, {
+        remainingAccounts: [
+          { pubkey: payingToken, isWritable: true, isSigner: false },
+          {
+            pubkey: transferAuthority.publicKey,
+            isWritable: false,
+            isSigner: true,
+          },
+        ],
+        signers: [transferAuthority],
+        instructions: [fund(this.authority.publicKey, 10000000)],
+      });
+
+      const walletTokens = await connection.getTokenAccountBalance(walletToken);
+      assert.equal(walletTokens.value.uiAmount, 1.5);
+      const payingTokens = await connection.getTokenAccountBalance(payingToken);
+      assert.equal(payingTokens.value.uiAmount, 0.5);
+    });
+  });
+
+  describe("payer as wallet", function () {
+    it("skips the SOL transfer to itself", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      const { tx } = await mintNft(this, myWallet, myWallet.publicKey);
+      await connection.confirmTransaction(tx, "confirmed");
+      const { meta } = await connection.getTransaction(tx, {
+        commitment: "confirmed",
+      });
+      // the candy machine itself never calls the system program
+      assert.ok(
+        !meta.logMessages.includes(
+          `Program ${SystemProgram.programId.toBase58()} invoke [2]`
+        )
+      );
+    });
+  });
+
+  describe("reveal", function () {
+    it("rewrites a minted item to its real config line", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        retainAuthority: true,
+        data: { price: new anchor.BN(0), goLiveDate: new anchor.BN(0) },
+      });
+      const buyer = anchor.web3.Keypair.generate();
+      const { metadata } = await mintNft(this, buyer, myWallet.publicKey, {
+        updateAuthority: this.authority.publicKey,
+        instructions: [fund(buyer.publicKey, 10000000)],
+      });
+      assert.equal((await getMetadataData(metadata)).name, "Sample 0");
+
+      await program.rpc.reveal(3, {
+        accounts: {
+          config: this.config.publicKey,
+          candyMachine,
+          authority: this.authority.publicKey,
+          metadata,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        },
+        signers: [this.authority],
+      });
+      assert.equal((await getMetadataData(metadata)).name, "Sample 3");
+    });
+  });
+
+  describe("lock metadata batch", function () {
+    it("locks several minted items at once", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        retainAuthority: true,
+        data: { price: new anchor.BN(0), goLiveDate: new anchor.BN(0) },
+      });
+      const metadatas = [];
+      for (let i = 0; i < 2; i++) {
+        const buyer = anchor.web3.Keypair.generate();
+        const { metadata } = await mintNft(this, buyer, myWallet.publicKey, {
+          updateAuthority: this.authority.publicKey,
+          instructions: [fund(buyer.publicKey, 10000000)],
+        });
+        metadatas.push(metadata);
+      }
+
+      await program.rpc.lockMetadataBatch({
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        },
+        remainingAccounts: metadatas.map((pubkey) => ({
+          pubkey,
+          isWritable: true,
+          isSigner: false,
+        })),
+        signers: [this.authority],
+      });
+      for (const metadata of metadatas) {
+        const { updateAuthority } = await getMetadataData(metadata);
+        assert.ok(updateAuthority.equals(new PublicKey(0)));
+      }
+    });
+  });
+
+  describe("assigned indices", function () {
+    it("lets only the assigned wallet mint an index", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 10,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      const assignee = anchor.web3.Keypair.generate();
+      const index = new anchor.BN(7);
+      const [assignment, bump] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          candyMachine.toBuffer(),
+          index.toArrayLike(Buffer, "le", 8),
+        ],
+        programId
+      );
+      await program.rpc.assignIndex(bump, index, assignee.publicKey, {
+        accounts: {
+          candyMachine,
+          indexAssignment: assignment,
+          authority: this.authority.publicKey,
+          payer: myWallet.publicKey,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
+        },
+        signers: [this.authority, myWallet],
+      });
+      await program.rpc.markRedeemed(index, {
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+      const remainingAccounts = [
+        { pubkey: assignment, isWritable: false, isSigner: false },
+      ];
+
+      const outsider = anchor.web3.Keypair.generate();
+      try {
+        await mintNft(this, outsider, myWallet.publicKey, {
+          remainingAccounts,
+          instructions: [fund(outsider.publicKey, 1000000000 + 10000000)],
+        });
+        assert.fail("minted an index assigned to another wallet");
+      } catch (e) {
+        assert.equal(e.msg, "This index is assigned to another wallet");
+      }
+
+      const { metadata } = await mintNft(
+        this,
+        assignee,
+        myWallet.publicKey,
+        {
+          remainingAccounts,
+          instructions: [fund(assignee.publicKey, 1000000000 + 10000000)],
+        }
+      );
+      assert.equal((await getMetadataData(metadata)).name, "Sample 7");
+    });
+
+    it("recovers once the authority unassigns an absent wallet's index", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 10,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      const index = new anchor.BN(0);
+      const [assignment, bump] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          candyMachine.toBuffer(),
+          index.toArrayLike(Buffer, "le", 8),
+        ],
+        programId
+      );
+      await program.rpc.assignIndex(
+        bump,
+        index,
+        anchor.web3.Keypair.generate().publicKey,
+        {
+          accounts: {
+            candyMachine,
+            indexAssignment: assignment,
+            authority: this.authority.publicKey,
+            payer: myWallet.publicKey,
+            systemProgram: SystemProgram.programId,
+            rent: SYSVAR_RENT_PUBKEY,
+          },
+          signers: [this.authority, myWallet],
+        }
+      );
+
+      const outsider = anchor.web3.Keypair.generate();
+      try {
+        await mintNft(this, outsider, myWallet.publicKey, {
+          remainingAccounts: [
+            { pubkey: assignment, isWritable: false, isSigner: false },
+          ],
+          instructions: [fund(outsider.publicKey, 1000000000 + 10000000)],
+        });
+        assert.fail("minted an index assigned to another wallet");
+      } catch (e) {
+        assert.equal(e.msg, "This index is assigned to another wallet");
+      }
+
+      await program.rpc.unassignIndex(index, {
+        accounts: {
+          candyMachine,
+          indexAssignment: assignment,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+      assert.equal(await connection.getAccountInfo(assignment), null);
+
+      const { metadata } = await mintNft(this, outsider, myWallet.publicKey, {
+        instructions: [fund(outsider.publicKey, 1000000000 + 10000000)],
+      });
+      assert.equal((await getMetadataData(metadata)).name, "Sample 0");
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.equal(machine.assignedIndices, 0);
+      assert.equal(machine.itemsRedeemed.toNumber(), 1);
+    });
+  });
+
+  describe("delegated payment", function () {
+    it("lets another signer pay the price", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      const buyer = anchor.web3.Keypair.generate();
+      const feePayer = anchor.web3.Keypair.generate();
+      await mintNft(this, buyer, myWallet.publicKey, {
+        delegatedPayment: true,
+        remainingAccounts: [
+          { pubkey: feePayer.publicKey, isWritable: true, isSigner: true },
+        ],
+        signers: [feePayer],
+        instructions: [
+          fund(buyer.publicKey, 10000000),
+          fund(feePayer.publicKey, 1000000000),
+        ],
+      });
+
+      assert.equal(await connection.getBalance(feePayer.publicKey), 0);
+      assert.ok((await connection.getBalance(buyer.publicKey)) < 10000000);
+    });
+  });
+
+  describe("rate limit", function () {
+    it("lets a wallet mint again once its window rolled over", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0), goLiveDate: new anchor.BN(0) },
+      });
+      await program.rpc.setRateLimit(
+        { maxMints: 1, windowSeconds: new anchor.BN(2) },
+        {
+          accounts: {
+            candyMachine,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        }
+      );
+
+      const buyer = anchor.web3.Keypair.generate();
+      const [walletMints, bump] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          Buffer.from("wallet_mints"),
+          candyMachine.toBuffer(),
+          buyer.publicKey.toBuffer(),
+        ],
+        programId
+      );
+      await program.rpc.initializeWalletMints(bump, {
+        accounts: {
+          candyMachine,
+          walletMints,
+          payer: buyer.publicKey,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
+        },
+        signers: [buyer],
+        instructions: [fund(buyer.publicKey, 100000000)],
+      });
+      const extra = {
+        remainingAccounts: [
+          { pubkey: walletMints, isWritable: true, isSigner: false },
+        ],
+      };
+
+      await mintNft(this, buyer, myWallet.publicKey, extra);
+      try {
+        await mintNft(this, buyer, myWallet.publicKey, extra);
+        assert.fail("minted twice in one window");
+      } catch (e) {
+        assert.equal(e.msg, "Wallet reached its mint limit for this window");
+      }
+
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+      const { metadata } = await mintNft(
+        this,
+        buyer,
+        myWallet.publicKey,
+        extra
+      );
+      const metadataAccount = await connection.getAccountInfo(metadata);
+      assert.ok(metadataAccount.data.length > 0);
+    });
+  });
+
+  describe("pass allocation", function () {
+    it("lets a wallet mint as many items as it holds passes", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0), goLiveDate: new anchor.BN(0) },
+      });
+      const passMint = await Token.createMint(
+        connection,
+        myWallet,
+        myWallet.publicKey,
+        null,
+        0,
+        TOKEN_PROGRAM_ID
+      );
+      await program.rpc.setPassMint(passMint.publicKey, {
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+
+      const buyer = anchor.web3.Keypair.generate();
+      const passToken = await passMint.createAssociatedTokenAccount(
+        buyer.publicKey
+      );
+      await passMint.mintTo(passToken, myWallet, [], 3);
+      const [walletMints, bump] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          Buffer.from("wallet_mints"),
+          candyMachine.toBuffer(),
+          buyer.publicKey.toBuffer(),
+        ],
+        programId
+      );
+      await program.rpc.initializeWalletMints(bump, {
+        accounts: {
+          candyMachine,
+          walletMints,
+          payer: buyer.publicKey,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
+        },
+        signers: [buyer],
+        instructions: [fund(buyer.publicKey, 100000000)],
+      });
+      const extra = {
+        remainingAccounts: [
+          { pubkey: walletMints, isWritable: true, isSigner: false },
+          { pubkey: passToken, isWritable: false, isSigner: false },
+        ],
+      };
+
+      for (let i = 0; i < 3; i++) {
+        await mintNft(this, buyer, myWallet.publicKey, extra);
+      }
+      try {
+        await mintNft(this, buyer, myWallet.publicKey, extra);
+        assert.fail("minted more items than passes held");
+      } catch (e) {
+        assert.equal(e.msg, "Wallet minted as many items as it holds passes");
+      }
+    });
+  });
+
+  describe("supply cap", function () {
+    it("caps mints across the candy machines sharing it", async function () {
+      const first = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      const firstUuid = this.candyMachineUuid;
+
+      const secondUuid = anchor.web3.Keypair.generate()
+        .publicKey.toBase58()
+        .slice(0, 6);
+      const [second, secondBump] = await getCandyMachine(
+        this.config.publicKey,
+        secondUuid
+      );
+      await program.rpc.initializeCandyMachine(
+        secondBump,
+        {
+          uuid: secondUuid,
+          price: new anchor.BN(0),
+          itemsAvailable: new anchor.BN(5),
+          goLiveDate: null,
+        },
+        noMintOptions,
+        null,
+        false,
+        {
+          accounts: {
+            candyMachine: second,
+            wallet: myWallet.publicKey,
+            config: this.config.publicKey,
+            authority: this.authority.publicKey,
+            payer: myWallet.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          },
+          signers: [myWallet, this.authority],
+        }
+      );
+
+      const [supplyCap, bump] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          Buffer.from("supply_cap"),
+          this.authority.publicKey.toBuffer(),
+          Buffer.from(firstUuid),
+        ],
+        programId
+      );
+      await program.rpc.initializeSupplyCap(
+        bump,
+        firstUuid,
+        new anchor.BN(3),
+        {
+          accounts: {
+            supplyCap,
+            authority: this.authority.publicKey,
+            payer: myWallet.publicKey,
+            systemProgram: SystemProgram.programId,
+            rent: SYSVAR_RENT_PUBKEY,
+          },
+          signers: [this.authority, myWallet],
+        }
+      );
+      for (const candyMachine of [first, second]) {
+        await program.rpc.setSupplyCap({
+          accounts: {
+            candyMachine,
+            supplyCap,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        });
+      }
+
+      const remainingAccounts = [
+        { pubkey: supplyCap, isWritable: true, isSigner: false },
+      ];
+      const buyer = anchor.web3.Keypair.generate();
+      for (const uuid of [firstUuid, secondUuid, firstUuid]) {
+        this.candyMachineUuid = uuid;
+        await mintNft(this, buyer, myWallet.publicKey, {
+          remainingAccounts,
+          instructions: [fund(buyer.publicKey, 10000000)],
+        });
+      }
+
+      for (const uuid of [firstUuid, secondUuid]) {
+        this.candyMachineUuid = uuid;
+        try {
+          await mintNft(this, buyer, myWallet.publicKey, {
+            remainingAccounts,
+            instructions: [fund(buyer.publicKey, 10000000)],
+          });
+          assert.fail("minted past the shared supply cap");
+        } catch (e) {
+          assert.equal(e.msg, "Candy machine is empty!");
+        }
+      }
+
+      try {
+        const recipient = anchor.web3.Keypair.generate();
+        await forceMint(this, recipient, remainingAccounts);
+        assert.fail("force minted past the shared supply cap");
+      } catch (e) {
+        assert.equal(e.msg, "Candy machine is empty!");
+      }
+    });
+  });
+
+  describe("relayer", function () {
+    it("is reimbursed from the candy machine balance", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      // the provider wallet submits, and so pays for, every transaction
+      await program.rpc.setRelayer(myWallet.publicKey, new anchor.BN(5000), {
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+        instructions: [fund(candyMachine, 100000)],
+      });
+      const balance = await connection.getBalance(candyMachine);
+
+      const buyer = anchor.web3.Keypair.generate();
+      await mintNft(this, buyer, myWallet.publicKey, {
+        remainingAccounts: [
+          { pubkey: myWallet.publicKey, isWritable: true, isSigner: true },
+        ],
+        instructions: [fund(buyer.publicKey, 1000000000 + 10000000)],
+      });
+      assert.equal(await connection.getBalance(candyMachine), balance - 5000);
+    });
+
+    it("refuses a co-signer that isn't the relayer", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      await program.rpc.setRelayer(myWallet.publicKey, new anchor.BN(5000), {
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+        instructions: [fund(candyMachine, 100000)],
+      });
+
+      const buyer = anchor.web3.Keypair.generate();
+      const coSigner = anchor.web3.Keypair.generate();
+      try {
+        await mintNft(this, buyer, myWallet.publicKey, {
+          remainingAccounts: [
+            { pubkey: coSigner.publicKey, isWritable: true, isSigner: true },
+          ],
+          signers: [coSigner],
+          instructions: [fund(buyer.publicKey, 1000000000 + 10000000)],
+        });
+        assert.fail("reimbursed a co-signer that isn't the relayer");
+      } catch (e) {
+        assert.equal(e.msg, "Relayer must be the candy machine's relayer and sign");
+      }
+    });
+
+    it("caps the reimbursement at the fees of a mint", async function () {
+      const candyMachine = await initializeCandyMachine(this, { size: 5 });
+
+      try {
+        await program.rpc.setRelayer(
+          myWallet.publicKey,
+          new anchor.BN(1000000),
+          {
+            accounts: {
+              candyMachine,
+              authority: this.authority.publicKey,
+            },
+            signers: [this.authority],
+          }
+        );
+        assert.fail("set a reimbursement above the fees of a mint");
+      } catch (e) {
+        assert.equal(e.msg, "Relayer reimbursement is above the fees of a mint");
+      }
+    });
+  });
+
+  describe("launchpad escrow", function () {
+    it("mints into the launchpad's escrow", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      const [launchpad] = await PublicKey.findProgramAddress(
+        [Buffer.from("escrow")],
+        anchor.web3.Keypair.generate().publicKey
+      );
+      await program.rpc.setLaunchpad(launchpad, {
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+
+      const { token } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        {
+          escrowOwner: launchpad,
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        }
+      );
+      const escrow = await connection.getParsedAccountInfo(token);
+      const { info } = (escrow.value.data as any).parsed;
+      assert.equal(info.owner, launchpad.toBase58());
+      assert.equal(info.tokenAmount.amount, "1");
+    });
+  });
+
+  describe("memo", function () {
+    it("is logged by the memo program", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      const { tx } = await mintNft(this, this.authority, myWallet.publicKey, {
+        memo: "order-42",
+        instructions: [fund(this.authority.publicKey, 10000000)],
+      });
+      await connection.confirmTransaction(tx, "confirmed");
+      const { meta } = await connection.getTransaction(tx, {
+        commitment: "confirmed",
+      });
+      assert.ok(
+        meta.logMessages.includes(
+          `Program ${SPL_MEMO_PROGRAM_ID.toBase58()} invoke [2]`
+        )
+      );
+      assert.ok(
+        meta.logMessages.includes('Program log: Memo (len 8): "order-42"')
+      );
+    });
+  });
+
+  describe("payment vault", function () {
+    it("pays the price from the payer's vault", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(1000000) },
+      });
+      const [vault, bump] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          Buffer.from("payment_vault"),
+          this.authority.publicKey.toBuffer(),
+        ],
+        programId
+      );
+      const { token } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        {
+          remainingAccounts: [
+            { pubkey: vault, isWritable: true, isSigner: false },
+          ],
+          paymentVaultBump: bump,
+          instructions: [
+            fund(this.authority.publicKey, 10000000),
+            fund(vault, 1000000),
+          ],
+        }
+      );
+
+      assert.equal(await connection.getBalance(vault), 0);
+      assert.equal(
+        (await connection.getTokenAccountBalance(token)).value.uiAmount,
+        1
+      );
+    });
+
+    it("lets the payer withdraw from their vault", async function () {
+      const payer = anchor.web3.Keypair.generate();
+      const destination = anchor.web3.Keypair.generate().publicKey;
+      const [vault, bump] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          Buffer.from("payment_vault"),
+          payer.publicKey.toBuffer(),
+        ],
+        programId
+      );
+
+      await program.rpc.withdrawPaymentVault(bump, new anchor.BN(3000000), {
+        accounts: {
+          payer: payer.publicKey,
+          vault,
+          destination,
+          systemProgram: SystemProgram.programId,
+        },
+        instructions: [fund(vault, 3000000)],
+        signers: [payer],
+      });
+
+      assert.equal(await connection.getBalance(vault), 0);
+      assert.equal(await connection.getBalance(destination), 3000000);
+    });
+
+    it("refuses to withdraw another payer's vault", async function () {
+      const owner = anchor.web3.Keypair.generate();
+      const thief = anchor.web3.Keypair.generate();
+      const [vault, bump] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          Buffer.from("payment_vault"),
+          owner.publicKey.toBuffer(),
+        ],
+        programId
+      );
+
+      try {
+        await program.rpc.withdrawPaymentVault(bump, new anchor.BN(3000000), {
+          accounts: {
+            payer: thief.publicKey,
+            vault,
+            destination: thief.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          instructions: [fund(vault, 3000000)],
+          signers: [thief],
+        });
+        assert.fail("withdrew from another payer's vault");
+      } catch (e) {
+        assert.equal(e.msg, "Payment vault isn't the payer's vault");
+      }
+    });
+  });
+
+  describe("associated token account", function () {
+    it("creates the payer's token account and mints into it", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+      });
+      const { token } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        {
+          mintToAta: true,
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        }
+      );
+
+      const balance = await connection.getTokenAccountBalance(token);
+      assert.equal(balance.value.amount, "1");
+    });
+  });
+
+  describe("proof of work", function () {
+    const powBits = function (
+      candyMachine: PublicKey,
+      payer: PublicKey,
+      nonce: anchor.BN
+    ): number {
+      const hash: Buffer = require("crypto")
+        .createHash("sha256")
+        .update(candyMachine.toBuffer())
+        .update(payer.toBuffer())
+        .update(nonce.toArrayLike(Buffer, "le", 8))
+        .digest();
+      let bits = 0;
+      for (const byte of hash) {
+        bits += byte == 0 ? 8 : Math.clz32(byte) - 24;
+        if (byte != 0) break;
+      }
+      return bits;
+    };
+
+    const findNonce = function (
+      candyMachine: PublicKey,
+      payer: PublicKey,
+      valid: boolean
+    ): anchor.BN {
+      let nonce = new anchor.BN(0);
+      while ((powBits(candyMachine, payer, nonce) >= 8) != valid) {
+        nonce = nonce.addn(1);
+      }
+      return nonce;
+    };
+
+    beforeEach(async function () {
+      this.candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: {
+          price: new anchor.BN(0),
+          goLiveDate: new anchor.BN(0),
+        },
+        mintOptions: { powDifficulty: 8 },
+      });
+      this.buyer = anchor.web3.Keypair.generate();
+    });
+
+    it("mints with a valid nonce", async function () {
+      const { metadata } = await mintNft(
+        this,
+        this.buyer,
+        myWallet.publicKey,
+        {
+          powNonce: findNonce(this.candyMachine, this.buyer.publicKey, true),
+          instructions: [fund(this.buyer.publicKey, 10000000)],
+        }
+      );
+      const metadataAccount = await connection.getAccountInfo(metadata);
+      assert.ok(metadataAccount.data.length > 0);
+    });
+
+    it("rejects an invalid nonce", async function () {
+      try {
+        await mintNft(this, this.buyer, myWallet.publicKey, {
+          powNonce: findNonce(this.candyMachine, this.buyer.publicKey, false),
+          instructions: [fund(this.buyer.publicKey, 10000000)],
+        });
+        assert.fail("minted with an invalid nonce");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Nonce is not a valid proof of work for this candy machine and payer"
+        );
+      }
+    });
+  });
+
+  describe("price guard", function () {
+    it("rejects an absurd price when the guard is enabled", async function () {
+      try {
+        await initializeCandyMachine(this, {
+          size: 5,
+          data: { price: new anchor.BN("1000000000000000000") },
+          maxPriceGuard: new anchor.BN(100000000000),
+        });
+        assert.fail("initialized with a price above the guard");
+      } catch (e) {
+        assert.equal(e.msg, "Price is above the supplied max price guard");
+      }
+    });
+
+    it("accepts the same price without a guard", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN("1000000000000000000") },
+      });
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.equal(machine.data.price.toString(), "1000000000000000000");
+    });
+
+    it("rejects an update above the guard", async function () {
+      const candyMachine = await initializeCandyMachine(this, { size: 5 });
+      try {
+        await program.rpc.updateCandyMachine(
+          new anchor.BN("1000000000000000000"),
+          null,
+          new anchor.BN(100000000000),
+          {
+            accounts: {
+              candyMachine,
+              authority: this.authority.publicKey,
+            },
+            signers: [this.authority],
+          }
+        );
+        assert.fail("updated to a price above the guard");
+      } catch (e) {
+        assert.equal(e.msg, "Price is above the supplied max price guard");
+      }
+    });
+  });
+
+  describe("max supply guard", function () {
+    it("rejects a max supply above the guard", async function () {
+      try {
+        await initializeCandyMachine(this, {
+          size: 5,
+          configOverrides: {
+            maxSupply: new anchor.BN("18446744073709551615"),
+          },
+          maxSupplyGuard: new anchor.BN(0),
+        });
+        assert.fail("initialized with a max supply above the guard");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Max supply is above the supplied max supply guard"
+        );
+      }
+    });
+  });
+
+  describe("freeze authority", function () {
+    beforeEach(async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+    });
+
+    it("ends up with the master edition", async function () {
+      const { mint, masterEdition } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        { instructions: [fund(this.authority.publicKey, 10000000)] }
+      );
+      const mintInfo = await new Token(
+        connection,
+        mint.publicKey,
+        TOKEN_PROGRAM_ID,
+        myWallet
+      ).getMintInfo();
+      assert.ok(mintInfo.freezeAuthority.equals(masterEdition));
+    });
+
+    it("rejects a separate freeze authority before the CPIs", async function () {
+      try {
+        await mintNft(this, this.authority, myWallet.publicKey, {
+          freezeAuthority: anchor.web3.Keypair.generate().publicKey,
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        });
+        assert.fail("minted with a separate freeze authority");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Freeze authority of the new token must be empty or the mint authority"
+        );
+      }
+    });
+  });
+
+  describe("items redeemed", function () {
+    it("is unchanged when a metadata CPI fails", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      let failed = false;
+      try {
+        await mintNft(this, this.authority, myWallet.publicKey, {
+          // not the metadata PDA of the new mint, token metadata rejects it
+          metadata: anchor.web3.Keypair.generate().publicKey,
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        });
+      } catch (e) {
+        failed = true;
+      }
+      assert.ok(failed, "minted with a bogus metadata account");
+      let machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.equal(machine.itemsRedeemed.toNumber(), 0);
+
+      await mintNft(this, this.authority, myWallet.publicKey, {
+        instructions: [fund(this.authority.publicKey, 10000000)],
+      });
+      machine = await program.account.candyMachine.fetch(candyMachine);
+      assert.equal(machine.itemsRedeemed.toNumber(), 1);
+    });
+
+    it("rejects an existing metadata account early", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      const { metadata } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        { instructions: [fund(this.authority.publicKey, 10000000)] }
+      );
+
+      try {
+        await mintNft(this, this.authority, myWallet.publicKey, {
+          metadata,
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        });
+        assert.fail("minted over an existing metadata account");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Metadata or master edition account already exists"
+        );
+      }
+    });
+
+    it("can be marked for items minted elsewhere", async function () {
+      const candyMachine = await initializeCandyMachine(this, { size: 10 });
+      const markRedeemed = (amount: number) =>
+        program.rpc.markRedeemed(new anchor.BN(amount), {
+          accounts: {
+            candyMachine,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        });
+      await markRedeemed(5);
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.equal(machine.itemsRedeemed.toNumber(), 5);
+      assert.equal(
+        machine.data.itemsAvailable.sub(machine.itemsRedeemed).toNumber(),
+        5
+      );
+
+      try {
+        await markRedeemed(6);
+        assert.fail("marked more items than are available");
+      } catch (e) {
+        assert.equal(e.msg, "Items redeemed can't exceed items available");
+      }
+    });
+  });
+
+  describe("creator verification", function () {
+    it("can be deferred to a batch after minting", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        configOverrides: { deferCreatorVerification: true },
+        data: { price: new anchor.BN(0) },
+      });
+      const minted = [];
+      for (let i = 0; i < 2; i++) {
+        minted.push(
+          await mintNft(this, this.authority, myWallet.publicKey, {
+            instructions: [fund(this.authority.publicKey, 10000000)],
+          })
+        );
+      }
+      for (const { metadata } of minted) {
+        const [creator] = (await getMetadataData(metadata)).creators;
+        assert.ok(creator.address.equals(candyMachine));
+        assert.ok(!creator.verified);
+      }
+
+      await program.rpc.signMetadataBatch({
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        },
+        remainingAccounts: minted.map(({ metadata }) => ({
+          pubkey: metadata,
+          isWritable: true,
+          isSigner: false,
+        })),
+        signers: [this.authority],
+      });
+      for (const { metadata } of minted) {
+        const [creator] = (await getMetadataData(metadata)).creators;
+        assert.ok(creator.verified);
+      }
+    });
+  });
+
+  describe("external url", function () {
+    it("is emitted with every mint", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        configOverrides: { externalUrl: "https://example.com" },
+        data: { price: new anchor.BN(0) },
+      });
+      let minted = null;
+      const event = await captureEvent("MintEvent", async () => {
+        minted = await mintNft(this, this.authority, myWallet.publicKey, {
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        });
+      });
+
+      assert.ok(event.mint.equals(minted.mint.publicKey));
+      assert.equal(event.externalUrl, "https://example.com");
+    });
+  });
+
+  describe("mint event", function () {
+    it("carries the minted line's trimmed name and uri", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      const event = await captureEvent("MintEvent", async () => {
+        await mintNft(this, this.authority, myWallet.publicKey, {
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        });
+      });
+
+      assert.equal(event.index.toNumber(), 0);
+      assert.equal(event.name, "Sample 0");
+      assert.equal(event.uri, "www.aol.com");
+    });
+  });
+
+  describe("stats", function () {
+    it("emits items sold and price collected", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(1000000) },
+      });
+      for (let i = 0; i < 2; i++) {
+        await mintNft(this, this.authority, myWallet.publicKey, {
+          instructions: [fund(this.authority.publicKey, 20000000)],
+        });
+      }
+
+      const event = await captureEvent("StatsEvent", async () => {
+        await program.rpc.emitStats({
+          accounts: {
+            candyMachine,
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          },
+        });
+      });
+
+      assert.ok(event.candyMachine.equals(candyMachine));
+      assert.equal(event.itemsRedeemed.toNumber(), 2);
+      assert.equal(event.itemsAvailable.toNumber(), 5);
+      assert.equal(event.totalCollected.toNumber(), 2000000);
+      assert.ok(event.timestamp.toNumber() > 0);
+    });
+  });
+
+  describe("authority presale", function () {
+    const setUp = async function (that, mode) {
+      const wallet = anchor.web3.Keypair.generate().publicKey;
+      const candyMachine = await initializeCandyMachine(that, {
+        size: 5,
+        wallet,
+        data: {
+          price: new anchor.BN(100000000),
+          goLiveDate: new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        },
+        instructions: [fund(wallet, 1000000)],
+      });
+      await program.rpc.setAuthorityPresaleMode(mode, {
+        accounts: {
+          candyMachine,
+          authority: that.authority.publicKey,
+        },
+        signers: [that.authority],
+      });
+      return wallet;
+    };
+
+    it("charges the authority in charged mode", async function () {
+      const wallet = await setUp(this, { charged: {} });
+      await mintNft(this, this.authority, wallet, {
+        instructions: [fund(this.authority.publicKey, 120000000)],
+      });
+
+      assert.equal(await connection.getBalance(wallet), 101000000);
+    });
+
+    it("lets the authority mint for free in free mode", async function () {
+      const wallet = await setUp(this, { free: {} });
+      await mintNft(this, this.authority, wallet, {
+        instructions: [fund(this.authority.publicKey, 20000000)],
+      });
+
+      assert.equal(await connection.getBalance(wallet), 1000000);
+    });
+
+    it("makes the authority wait in disabled mode", async function () {
+      const wallet = await setUp(this, { disabled: {} });
+      try {
+        await mintNft(this, this.authority, wallet, {
+          instructions: [fund(this.authority.publicKey, 120000000)],
+        });
+        assert.fail("authority minted before go-live");
+      } catch (e) {
+        assert.equal(e.msg, "Candy machine is not live yet!");
+      }
+    });
+  });
+
+  describe("refund window", function () {
+    const setUp = async function (that, refundWindow: number) {
+      const wallet = anchor.web3.Keypair.generate().publicKey;
+      const candyMachine = await initializeCandyMachine(that, {
+        size: 5,
+        wallet,
+        data: { price: new anchor.BN(100000000) },
+        instructions: [fund(wallet, 1000000)],
+      });
+      await program.rpc.setRefundWindow(new anchor.BN(refundWindow), {
+        accounts: {
+          candyMachine,
+          authority: that.authority.publicKey,
+        },
+        signers: [that.authority],
+      });
+      const { mint, token } = await mintNft(that, that.authority, wallet, {
+        refundable: true,
+        instructions: [fund(that.authority.publicKey, 120000000)],
+      });
+      return { candyMachine, wallet, mint, token };
+    };
+
+    const cancelMint = async function (candyMachine, buyer, mint, token) {
+      await program.rpc.cancelMint({
+        accounts: {
+          candyMachine,
+          mintEscrow: await getMintEscrow(mint.publicKey),
+          buyer: buyer.publicKey,
+          mint: mint.publicKey,
+          tokenAccount: token,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+        signers: [buyer],
+      });
+    };
+
+    it("refunds a mint cancelled within the window", async function () {
+      const { candyMachine, mint, token } = await setUp(this, 3600);
+      const escrow = await getMintEscrow(mint.publicKey);
+      const held = await connection.getBalance(escrow);
+      const before = await connection.getBalance(this.authority.publicKey);
+
+      await cancelMint(candyMachine, this.authority, mint, token);
+
+      assert.equal(await connection.getAccountInfo(escrow), null);
+      assert.equal(
+        (await connection.getTokenAccountBalance(token)).value.amount,
+        "0"
+      );
+      assert.equal(
+        await connection.getBalance(this.authority.publicKey),
+        before + held
+      );
+    });
+
+    it("refuses to cancel once the window closed", async function () {
+      const { candyMachine, wallet, mint, token } = await setUp(this, 1);
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+
+      try {
+        await cancelMint(candyMachine, this.authority, mint, token);
+        assert.fail("cancelled a mint after its refund window");
+      } catch (e) {
+        assert.equal(e.msg, "Refund window of this mint has closed");
+      }
+
+      await program.rpc.sweepMintEscrow({
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+          wallet,
+          mintEscrow: await getMintEscrow(mint.publicKey),
+          buyer: this.authority.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+        signers: [this.authority],
+      });
+      assert.equal(await connection.getBalance(wallet), 101000000);
+    });
+
+    it("lets a buyer cancel their own mint after go live", async function () {
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(100000000), goLiveDate: new anchor.BN(0) },
+      });
+      await program.rpc.setRefundWindow(new anchor.BN(3600), {
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+      const buyer = anchor.web3.Keypair.generate();
+      const { mint, token } = await mintNft(this, buyer, myWallet.publicKey, {
+        refundable: true,
+        instructions: [fund(buyer.publicKey, 120000000)],
+      });
+      const escrow = await getMintEscrow(mint.publicKey);
+      const held = await connection.getBalance(escrow);
+      const before = await connection.getBalance(buyer.publicKey);
+
+      await cancelMint(candyMachine, buyer, mint, token);
+
+      assert.equal(await connection.getAccountInfo(escrow), null);
+      assert.equal(await connection.getBalance(buyer.publicKey), before + held);
+      // the cancelled line stays redeemed, but its price is no longer collected
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.equal(machine.itemsRedeemed.toNumber(), 1);
+      assert.equal(machine.totalCollected.toNumber(), 0);
+    });
+
+    it("refuses a refund window on token prices", async function () {
+      const candyMachine = await initializeCandyMachine(this, { size: 5 });
+      const tokenMint = await Token.createMint(
+        connection,
+        myWallet,
+        myWallet.publicKey,
+        null,
+        0,
+        TOKEN_PROGRAM_ID
+      );
+      await program.rpc.setTokenMint({
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+          wallet: await tokenMint.createAssociatedTokenAccount(
+            myWallet.publicKey
+          ),
+        },
+        remainingAccounts: [
+          { pubkey: tokenMint.publicKey, isWritable: false, isSigner: false },
+        ],
+        signers: [this.authority],
+      });
+
+      try {
+        await program.rpc.setRefundWindow(new anchor.BN(3600), {
+          accounts: {
+            candyMachine,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        });
+        assert.fail("set a refund window on a token price");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Refund window only holds SOL prices, token payments can't be used with it"
+        );
+      }
+    });
+  });
+
+  describe("tips", function () {
+    it("sends the tip to the creator fund", async function () {
+      const creatorFund = anchor.web3.Keypair.generate().publicKey;
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+        mintOptions: { creatorFund },
+      });
+
+      await mintNft(this, this.authority, myWallet.publicKey, {
+        tipLamports: new anchor.BN(5000000),
+        remainingAccounts: [
+          { pubkey: creatorFund, isWritable: true, isSigner: false },
+        ],
+        instructions: [fund(this.authority.publicKey, 15000000)],
+      });
+
+      assert.equal(await connection.getBalance(creatorFund), 5000000);
+    });
+  });
+
+  describe("per line symbol", function () {
+    beforeEach(async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      await program.rpc.addConfigLines(
+        0,
+        [
+          {
+            name: "Sample 0",
+            uri: "www.aol.com",
+            symbol: "FIRST",
+            sellerFeeBasisPoints: null,
+          },
+          {
+            name: "Sample 1",
+            uri: "www.aol.com",
+            symbol: null,
+            sellerFeeBasisPoints: null,
+          },
+        ],
+        {
+          accounts: {
+            config: this.config.publicKey,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        }
+      );
+    });
+
+    it("uses the line symbol and falls back to the config symbol", async function () {
+      const symbols = [];
+      for (let i = 0; i < 2; i++) {
+        const { metadata } = await mintNft(
+          this,
+          this.authority,
+          myWallet.publicKey,
+          { instructions: [fund(this.authority.publicKey, 10000000)] }
+        );
+        symbols.push((await getMetadataData(metadata)).symbol);
+      }
+      assert.deepEqual(symbols, ["FIRST", "SYMBOL"]);
+    });
+
+    it("rejects a symbol or name longer than token-metadata allows", async function () {
+      const addLine = (name: string, symbol: string) =>
+        program.rpc.addConfigLines(
+          2,
+          [{ name, uri: "www.aol.com", symbol, sellerFeeBasisPoints: null }],
+          {
+            accounts: {
+              config: this.config.publicKey,
+              authority: this.authority.publicKey,
+            },
+            signers: [this.authority],
+          }
+        );
+
+      try {
+        await addLine("Sample 2", "S".repeat(11));
+        assert.fail("stored a symbol longer than MAX_SYMBOL_LENGTH");
+      } catch (e) {
+        assert.equal(e.msg, "Symbol is longer than token-metadata allows");
+      }
+      try {
+        await addLine("N".repeat(33), null);
+        assert.fail("stored a name longer than MAX_NAME_LENGTH");
+      } catch (e) {
+        assert.equal(e.msg, "Name is longer than token-metadata allows");
+      }
+    });
+  });
+
+  describe("per line seller fee", function () {
+    beforeEach(async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      await program.rpc.addConfigLines(
+        0,
+        [
+          {
+            name: "Sample 0",
+            uri: "www.aol.com",
+            symbol: null,
+            sellerFeeBasisPoints: 2500,
+          },
+          {
+            name: "Sample 1",
+            uri: "www.aol.com",
+            symbol: null,
+            sellerFeeBasisPoints: null,
+          },
+        ],
+        {
+          accounts: {
+            config: this.config.publicKey,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        }
+      );
+    });
+
+    it("uses the line seller fee and falls back to the config", async function () {
+      const fees = [];
+      for (let i = 0; i < 2; i++) {
+        const { metadata } = await mintNft(
+          this,
+          this.authority,
+          myWallet.publicKey,
+          { instructions: [fund(this.authority.publicKey, 10000000)] }
+        );
+        fees.push((await getMetadataData(metadata)).sellerFeeBasisPoints);
+      }
+      assert.deepEqual(fees, [2500, 500]);
+    });
+  });
+
+  describe("compact config", function () {
+    beforeEach(async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        retainAuthority: true,
+        configOverrides: { namePrefix: "Compact" },
+        lineSize: compactConfigLineSize,
+      });
+    });
+
+    it("stores only uris", async function () {
+      const config = await connection.getAccountInfo(this.config.publicKey);
+
+      const amountOfConfigs = new anchor.BN(
+        config.data.slice(configArrayStart, configArrayStart + 4),
+        "le"
+      );
+      assert.equal(amountOfConfigs.toNumber(), 5);
+      for (let i = 0; i < amountOfConfigs.toNumber(); i++) {
+        const thisSlice = config.data.slice(
+          configArrayStart + 4 + compactConfigLineSize * i,
+          configArrayStart + 4 + compactConfigLineSize * (i + 1)
+        );
+        const uri = fromUTF8Array([...thisSlice.slice(4, 204)]);
+        assert.equal(uri.replace(/\0/g, "").trim(), "www.aol.com");
+      }
+    });
+
+    it("mints with a generated name", async function () {
+      const { metadata } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        {
+          // add minting fees in there
+          instructions: [fund(this.authority.publicKey, 1000000000 + 10000000)],
+        }
+      );
+
+      assert.equal((await getMetadataData(metadata)).name, "Compact #1");
+    });
+
+    it("numbers items from the name offset", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        retainAuthority: true,
+        configOverrides: {
+          namePrefix: "Compact",
+          nameOffset: new anchor.BN(100),
+        },
+        lineSize: compactConfigLineSize,
+      });
+      const { metadata } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        {
+          instructions: [fund(this.authority.publicKey, 1000000000 + 10000000)],
+        }
+      );
+
+      assert.equal((await getMetadataData(metadata)).name, "Compact #101");
+    });
+  });
+
+  describe("priority window", function () {
+    beforeEach(async function () {
+      // holders of NFTs from another candy machine get to mint first
+      this.source = {};
+      this.sourceMachine = await initializeCandyMachine(this.source, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      this.held = await mintNft(
+        this.source,
+        this.source.authority,
+        myWallet.publicKey,
+        { instructions: [fund(this.source.authority.publicKey, 10000000)] }
+      );
+      this.holder = this.source.authority;
+    });
+
+    const holderAccounts = function (held) {
+      return [
+        { pubkey: held.token, isWritable: false, isSigner: false },
+        { pubkey: held.metadata, isWritable: false, isSigner: false },
+      ];
+    };
+
+    it("lets only holders mint before the public date", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: {
+          price: new anchor.BN(0),
+          goLiveDate: new anchor.BN(0),
+        },
+        mintOptions: {
+          publicDate: new anchor.BN(4102444800),
+          priorityCollection: this.sourceMachine,
+        },
+      });
+
+      const outsider = anchor.web3.Keypair.generate();
+      try {
+        await mintNft(this, outsider, myWallet.publicKey, {
+          remainingAccounts: holderAccounts(this.held),
+          instructions: [fund(outsider.publicKey, 10000000)],
+        });
+        assert.fail("minted without holding the priority collection");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Only holders of the priority collection can mint before the public date"
+        );
+      }
+
+      const { metadata } = await mintNft(
+        this,
+        this.holder,
+        myWallet.publicKey,
+        {
+          remainingAccounts: holderAccounts(this.held),
+          instructions: [fund(this.holder.publicKey, 10000000)],
+        }
+      );
+      const metadataAccount = await connection.getAccountInfo(metadata);
+      assert.ok(metadataAccount.data.length > 0);
+    });
+
+    it("lets everyone mint after the public date", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: {
+          price: new anchor.BN(0),
+          goLiveDate: new anchor.BN(0),
+        },
+        mintOptions: {
+          publicDate: new anchor.BN(1),
+          priorityCollection: this.sourceMachine,
+        },
+      });
+
+      const outsider = anchor.web3.Keypair.generate();
+      const { metadata } = await mintNft(
+        this,
+        outsider,
+        myWallet.publicKey,
+        { instructions: [fund(outsider.publicKey, 10000000)] }
+      );
+      const metadataAccount = await connection.getAccountInfo(metadata);
+      assert.ok(metadataAccount.data.length > 0);
+    });
+  });
+
+  describe("holder discount", function () {
+    beforeEach(async function () {
+      // holders of NFTs from a partner candy machine get a cheaper mint
+      this.source = {};
+      this.sourceMachine = await initializeCandyMachine(this.source, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      this.held = await mintNft(
+        this.source,
+        this.source.authority,
+        myWallet.publicKey,
+        { instructions: [fund(this.source.authority.publicKey, 10000000)] }
+      );
+      this.holder = this.source.authority;
+
+      this.wallet = anchor.web3.Keypair.generate().publicKey;
+      const candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        wallet: this.wallet,
+        data: {
+          price: new anchor.BN(1000000),
+          goLiveDate: new anchor.BN(0),
+        },
+      });
+      await program.rpc.setHolderDiscount(
+        { collection: this.sourceMachine, discountBasisPoints: 2500 },
+        {
+          accounts: {
+            candyMachine,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+          instructions: [fund(this.wallet, 10000000)],
+        }
+      );
+    });
+
+    const holderAccounts = function (held) {
+      return [
+        { pubkey: held.token, isWritable: false, isSigner: false },
+        { pubkey: held.metadata, isWritable: false, isSigner: false },
+      ];
+    };
+
+    it("charges holders the discounted price", async function () {
+      await mintNft(this, this.holder, this.wallet, {
+        remainingAccounts: holderAccounts(this.held),
+        holderDiscount: true,
+        instructions: [fund(this.holder.publicKey, 10000000)],
+      });
+
+      assert.equal(await connection.getBalance(this.wallet), 10750000);
+    });
+
+    it("charges everyone else the full price", async function () {
+      const outsider = anchor.web3.Keypair.generate();
+      try {
+        await mintNft(this, outsider, this.wallet, {
+          remainingAccounts: holderAccounts(this.held),
+          holderDiscount: true,
+          instructions: [fund(outsider.publicKey, 10000000)],
+        });
+        assert.fail("got the discount without holding the collection");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Only holders of the discount collection get the holder discount"
+        );
+      }
+
+      await mintNft(this, outsider, this.wallet, {
+        instructions: [fund(outsider.publicKey, 10000000)],
+      });
+
+      assert.equal(await connection.getBalance(this.wallet), 11000000);
+    });
+  });
+
+  describe("config gaps", function () {
+    const recomputeConfigCount = async function (that) {
+      await program.rpc.recomputeConfigCount({
+        accounts: {
+          config: that.config.publicKey,
+          authority: that.authority.publicKey,
+        },
+        signers: [that.authority],
+      });
+    };
+
+    it("refuses to mint a line that was never uploaded", async function () {
+      await initializeCandyMachine(this, {
+        size: 10,
+        uploadedLines: [0, 2, 3, 4, 5],
+        data: { price: new anchor.BN(0), itemsAvailable: new anchor.BN(5) },
+      });
+      await mintNft(this, this.authority, myWallet.publicKey, {
+        instructions: [fund(this.authority.publicKey, 10000000)],
+      });
+
+      try {
+        await mintNft(this, this.authority, myWallet.publicKey, {
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        });
+        assert.fail("minted a line that was never uploaded");
+      } catch (e) {
+        assert.equal(e.msg, "Config line at this index was never uploaded");
+      }
+    });
+
+    it("refuses a config with gaps when it must be full", async function () {
+      try {
+        await initializeCandyMachine(this, {
+          size: 10,
+          uploadedLines: [0, 2, 3, 4, 5],
+          data: { itemsAvailable: new anchor.BN(5) },
+          requireFullConfig: true,
+        });
+        assert.fail("initialized against a config with gaps");
+      } catch (e) {
+        assert.equal(e.msg, "Config upload is incomplete or has blank lines");
+      }
+    });
+
+    it("recomputes the count from the uploaded lines", async function () {
+      await initializeCandyMachine(this, {
+        size: 10,
+        uploadedLines: [0, 1, 2, 5],
+        data: { itemsAvailable: new anchor.BN(3) },
+      });
+      const count = async (that) => {
+        const config = await connection.getAccountInfo(that.config.publicKey);
+        return new anchor.BN(
+          config.data.slice(configArrayStart, configArrayStart + 4),
+          "le"
+        ).toNumber();
+      };
+
+      await recomputeConfigCount(this);
+
+      // the line after the gap is uploaded too
+      assert.equal(await count(this), 4);
+    });
+
+    it("refuses to recompute the count once minting started", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      await mintNft(this, this.authority, myWallet.publicKey, {
+        instructions: [fund(this.authority.publicKey, 10000000)],
+      });
+
+      try {
+        await recomputeConfigCount(this);
+        assert.fail("recomputed the count of a frozen config");
+      } catch (e) {
+        assert.equal(e.msg, "Config lines can't change once minting started");
+      }
+    });
+  });
+
+  describe("config line batches", function () {
+    it("rejects an oversized batch without writing it", async function () {
+      await initializeCandyMachine(this, {
+        size: 20,
+        uploadedLines: [0],
+        data: { price: new anchor.BN(0), itemsAvailable: new anchor.BN(1) },
+      });
+      const lines = [];
+      for (let i = 1; i < 12; i++) {
+        lines.push({
+          name: `Sample ${i}`,
+          uri: "www.aol.com",
+          symbol: null,
+          sellerFeeBasisPoints: null,
+        });
+      }
+
+      try {
+        await program.rpc.addConfigLines(1, lines, {
+          accounts: {
+            config: this.config.publicKey,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        });
+        assert.fail("added an oversized batch");
+      } catch (e) {
+        assert.equal(e.msg, "Too many config lines in one batch");
+      }
+      const config = await connection.getAccountInfo(this.config.publicKey);
+      assert.equal(config.data.readUInt32LE(configArrayStart), 1);
+    });
+  });
+
+  describe("verify uploaded", function () {
+    const verifyUploaded = async function (that, expected: number) {
+      await program.rpc.verifyUploaded(expected, {
+        accounts: { config: that.config.publicKey },
+      });
+    };
+
+    it("passes on a full upload and fails on a blank line", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      await verifyUploaded(this, 5);
+
+      await program.rpc.addConfigLines(
+        4,
+        [{ name: "", uri: "", symbol: null, sellerFeeBasisPoints: null }],
+        {
+          accounts: {
+            config: this.config.publicKey,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        }
+      );
+      try {
+        await verifyUploaded(this, 5);
+        assert.fail("verified an upload with a blank line");
+      } catch (e) {
+        assert.equal(e.msg, "Config upload is incomplete or has blank lines");
+      }
+    });
+  });
+
+  describe("frozen config", function () {
+    it("rejects new lines once an item was minted", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      await mintNft(this, this.authority, myWallet.publicKey, {
+        instructions: [fund(this.authority.publicKey, 10000000)],
+      });
+      const config: Config = await program.account.config.fetch(
+        this.config.publicKey
+      );
+      assert.ok(config.frozen);
+
+      try {
+        await program.rpc.addConfigLines(
+          1,
+          [
+            {
+              name: "Changed",
+              uri: "www.aol.com",
+              symbol: null,
+              sellerFeeBasisPoints: null,
+            },
+          ],
+          {
+            accounts: {
+              config: this.config.publicKey,
+              authority: this.authority.publicKey,
+            },
+            signers: [this.authority],
+          }
+        );
+        assert.fail("changed a line after minting started");
+      } catch (e) {
+        assert.equal(e.msg, "Config lines can't change once minting started");
+      }
+    });
+  });
+
+  describe("signed authorization", function () {
+    beforeEach(async function () {
+      this.candyMachine = await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      await program.rpc.setSignedAuthorization(true, {
+        accounts: {
+          candyMachine: this.candyMachine,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+      this.buyer = anchor.web3.Keypair.generate();
+    });
+
+    // the authorization terms, the message the authority signs over them, and the remaining
+    // accounts the mint needs: the instructions sysvar and the PDA recording the nonce
+    const authorize = async function (that, nonce: number, expiresAt: number) {
+      const authorization = {
+        nonce: new anchor.BN(nonce),
+        expiresAt: new anchor.BN(expiresAt),
+      };
+      const message = Buffer.concat([
+        that.candyMachine.toBuffer(),
+        that.buyer.publicKey.toBuffer(),
+        authorization.nonce.toArrayLike(Buffer, "le", 8),
+        authorization.expiresAt.toArrayLike(Buffer, "le", 8),
+      ]);
+      const [record, _] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(CANDY_MACHINE),
+          Buffer.from("mint_authorization"),
+          that.candyMachine.toBuffer(),
+          authorization.nonce.toArrayLike(Buffer, "le", 8),
+        ],
+        programId
+      );
+      const remainingAccounts = [
+        {
+          pubkey: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          isWritable: false,
+          isSigner: false,
+        },
+        { pubkey: record, isWritable: true, isSigner: false },
+      ];
+      return { authorization, message, remainingAccounts };
+    };
+    // 2100-01-01
+    const farFuture = 4102444800;
+
+    it("mints with a signature of the authority", async function () {
+      const { authorization, message, remainingAccounts } = await authorize(
+        this,
+        1,
+        farFuture
+      );
+      const { metadata } = await mintNft(
+        this,
+        this.buyer,
+        myWallet.publicKey,
+        {
+          authorization,
+          remainingAccounts,
+          instructions: [
+            fund(this.buyer.publicKey, 20000000),
+            createEd25519Instruction(this.authority, message),
+          ],
+        }
+      );
+
+      assert.equal((await getMetadataData(metadata)).name, "Sample 0");
+    });
+
+    it("rejects a signature of another key", async function () {
+      const { authorization, message, remainingAccounts } = await authorize(
+        this,
+        1,
+        farFuture
+      );
+      const forger = anchor.web3.Keypair.generate();
+      try {
+        await mintNft(this, this.buyer, myWallet.publicKey, {
+          authorization,
+          remainingAccounts,
+          instructions: [
+            fund(this.buyer.publicKey, 20000000),
+            createEd25519Instruction(forger, message),
+          ],
+        });
+        assert.fail("minted with a forged authorization");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Mint is not authorized by a signature of the candy machine authority"
+        );
+      }
+    });
+
+    it("rejects a signature used before", async function () {
+      const { authorization, message, remainingAccounts } = await authorize(
+        this,
+        2,
+        farFuture
+      );
+      const mint = () =>
+        mintNft(this, this.buyer, myWallet.publicKey, {
+          authorization,
+          remainingAccounts,
+          instructions: [
+            fund(this.buyer.publicKey, 20000000),
+            createEd25519Instruction(this.authority, message),
+          ],
+        });
+      await mint();
+      try {
+        await mint();
+        assert.fail("minted twice with one authorization");
+      } catch (e) {
+        assert.equal(e.msg, "Mint authorization was already used");
+      }
+    });
+
+    it("rejects an expired signature", async function () {
+      const { authorization, message, remainingAccounts } = await authorize(
+        this,
+        3,
+        1
+      );
+      try {
+        await mintNft(this, this.buyer, myWallet.publicKey, {
+          authorization,
+          remainingAccounts,
+          instructions: [
+            fund(this.buyer.publicKey, 20000000),
+            createEd25519Instruction(this.authority, message),
+          ],
+        });
+        assert.fail("minted with an expired authorization");
+      } catch (e) {
+        assert.equal(e.msg, "Mint authorization has expired");
+      }
+    });
+  });
+
+  describe("primary sale", function () {
+    it("marks minted items as sold", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      const { metadata } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        { instructions: [fund(this.authority.publicKey, 10000000)] }
+      );
+
+      assert.ok((await getMetadataData(metadata)).primarySaleHappened);
+    });
+  });
+
+  describe("withdraw delay", function () {
+    const setUp = async function (that, withdrawDelay: number) {
+      const wallet = anchor.web3.Keypair.generate().publicKey;
+      const candyMachine = await initializeCandyMachine(that, {
+        size: 5,
+        wallet,
+        configOverrides: { withdrawDelay: new anchor.BN(withdrawDelay) },
+        data: {
+          price: new anchor.BN(500000000),
+          goLiveDate: new anchor.BN(Math.floor(Date.now() / 1000)),
+        },
+        instructions: [fund(wallet, 1000000)],
+      });
+      return { candyMachine, wallet };
+    };
+
+    const releaseHeldProceeds = async function (that, candyMachine, wallet) {
+      await program.rpc.releaseHeldProceeds({
+        accounts: {
+          config: that.config.publicKey,
+          candyMachine,
+          authority: that.authority.publicKey,
+          wallet,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+        signers: [that.authority],
+      });
+    };
+
+    it("refuses to release before the delay after go live elapsed", async function () {
+      const { candyMachine, wallet } = await setUp(this, 3600);
+      await mintNft(this, this.authority, wallet, {
+        instructions: [fund(this.authority.publicKey, 510000000)],
+      });
+
+      try {
+        await releaseHeldProceeds(this, candyMachine, wallet);
+        assert.fail("released the proceeds right after go live");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Held proceeds can't be released until the withdraw delay after go live elapsed"
+        );
+      }
+    });
+
+    it("holds the price in the candy machine and pays it to the wallet", async function () {
+      const { candyMachine, wallet } = await setUp(this, 1);
+      const machineLamports = await connection.getBalance(candyMachine);
+      await mintNft(this, this.authority, wallet, {
+        instructions: [fund(this.authority.publicKey, 510000000)],
+      });
+      assert.equal(
+        await connection.getBalance(candyMachine),
+        machineLamports + 500000000
+      );
+      assert.equal(await connection.getBalance(wallet), 1000000);
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+
+      // a later mint doesn't push the release back
+      await mintNft(this, this.authority, wallet, {
+        instructions: [fund(this.authority.publicKey, 510000000)],
+      });
+      await releaseHeldProceeds(this, candyMachine, wallet);
+
+      assert.equal(await connection.getBalance(wallet), 1001000000);
+      assert.equal(await connection.getBalance(candyMachine), machineLamports);
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.equal(machine.heldProceeds.toNumber(), 0);
+    });
+
+    it("keeps the go live date while proceeds are held", async function () {
+      const { candyMachine, wallet } = await setUp(this, 3600);
+      await mintNft(this, this.authority, wallet, {
+        instructions: [fund(this.authority.publicKey, 510000000)],
+      });
+
+      try {
+        await program.rpc.updateCandyMachine(null, new anchor.BN(0), null, {
+          accounts: {
+            candyMachine,
+            authority: this.authority.publicKey,
+          },
+          signers: [this.authority],
+        });
+        assert.fail("moved the go live date of held proceeds");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Go live date can't change while proceeds are held for the withdraw delay"
+        );
+      }
+    });
+  });
+
+  describe("partial withdrawal", function () {
+    const withdrawFunds = async function (
+      that,
+      amount: anchor.BN,
+      instructions: anchor.web3.TransactionInstruction[] = []
+    ) {
+      await program.rpc.withdrawFunds(amount, null, {
+        accounts: {
+          config: that.config.publicKey,
+          authority: that.authority.publicKey,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        },
+        signers: [that.authority],
+        instructions,
+      });
+    };
+
+    it("withdraws the excess and keeps the config minting", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      const before = await connection.getBalance(this.config.publicKey);
+
+      await withdrawFunds(this, new anchor.BN(500000000), [
+        fund(this.config.publicKey, 500000000),
+      ]);
+
+      const after = await connection.getAccountInfo(this.config.publicKey);
+      assert.equal(after.lamports, before);
+      assert.ok(
+        after.lamports >=
+          (await connection.getMinimumBalanceForRentExemption(
+            after.data.length
+          ))
+      );
+
+      try {
+        await withdrawFunds(this, new anchor.BN(after.lamports));
+        assert.fail("withdrew the config below rent exemption");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Withdrawal would leave the config below rent exemption"
+        );
+      }
+      // the config is still there for the next buyer
+      await mintNft(this, this.authority, myWallet.publicKey, {
+        instructions: [fund(this.authority.publicKey, 10000000)],
+      });
+    });
+  });
+
+  describe("rent recipient", function () {
+    it("pays the closed config out to the recipient", async function () {
+      await initializeCandyMachine(this, { size: 5 });
+      const treasury = anchor.web3.Keypair.generate().publicKey;
+      const lamports = await connection.getBalance(this.config.publicKey);
+
+      await program.rpc.withdrawFunds(null, treasury, {
+        accounts: {
+          config: this.config.publicKey,
+          authority: this.authority.publicKey,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        },
+        remainingAccounts: [
+          { pubkey: treasury, isWritable: true, isSigner: false },
+        ],
+        signers: [this.authority],
+        instructions: [fund(treasury, 1000000)],
+      });
+
+      assert.equal(await connection.getBalance(treasury), lamports + 1000000);
+      assert.equal(
+        await connection.getAccountInfo(this.config.publicKey),
+        null
+      );
+    });
+  });
+
+  describe("base uri", function () {
+    it("mints with the base uri joined to the line suffix", async function () {
+      await initializeCandyMachine(this, {
+        size: 5,
+        configOverrides: { baseUri: "https://arweave.net/" },
+        lineSize: baseUriConfigLineSize,
+        data: { price: new anchor.BN(0) },
+      });
+      const { metadata } = await mintNft(
+        this,
+        this.authority,
+        myWallet.publicKey,
+        { instructions: [fund(this.authority.publicKey, 10000000)] }
+      );
+
+      assert.equal(
+        (await getMetadataData(metadata)).uri,
+        "https://arweave.net/www.aol.com"
+      );
+    });
+  });
+
+  describe("burn to redeem", function () {
+    beforeEach(async function () {
+      // an NFT from another candy machine is what gets burned
+      this.source = {};
+      const sourceMachine = await initializeCandyMachine(this.source, {
+        size: 5,
+        data: { price: new anchor.BN(0) },
+      });
+      this.burnable = await mintNft(
+        this.source,
+        this.source.authority,
+        myWallet.publicKey,
+        { instructions: [fund(this.source.authority.publicKey, 10000000)] }
+      );
+      this.buyer = this.source.authority;
+
+      await initializeCandyMachine(this, {
+        size: 5,
+        data: { goLiveDate: new anchor.BN(0) },
+        mintOptions: { burnToRedeemCreator: sourceMachine },
+      });
+    });
+
+    it("burns an eligible NFT for a new one", async function () {
+      const walletLamports = await connection.getBalance(myWallet.publicKey);
+      const { metadata } = await mintNft(
+        this,
+        this.buyer,
+        myWallet.publicKey,
+        {
+          remainingAccounts: [
+            { pubkey: this.burnable.token, isWritable: true, isSigner: false },
+            {
+              pubkey: this.burnable.mint.publicKey,
+              isWritable: true,
+              isSigner: false,
+            },
+            {
+              pubkey: this.burnable.metadata,
+              isWritable: false,
+              isSigner: false,
+            },
+            { pubkey: this.buyer.publicKey, isWritable: false, isSigner: true },
+          ],
+          instructions: [fund(this.buyer.publicKey, 10000000)],
+        }
+      );
+
+      const metadataAccount = await connection.getAccountInfo(metadata);
+      assert.ok(metadataAccount.data.length > 0);
+      const burnedBalance = await connection.getTokenAccountBalance(
+        this.burnable.token
+      );
+      assert.equal(burnedBalance.value.uiAmount, 0);
+      // nothing was paid into the wallet beyond the fees myWallet fronted
+      assert.ok(
+        walletLamports - (await connection.getBalance(myWallet.publicKey)) <
+          1000000000
+      );
+    });
+
+    it("rejects a token without qualifying metadata", async function () {
+      const mint = await Token.createMint(
+        connection,
+        myWallet,
+        myWallet.publicKey,
+        null,
+        0,
+        TOKEN_PROGRAM_ID
+      );
+      const token = await mint.createAssociatedTokenAccount(
+        this.buyer.publicKey
+      );
+      await mint.mintTo(token, myWallet, [], 1);
+
+      try {
+        await mintNft(this, this.buyer, myWallet.publicKey, {
+          remainingAccounts: [
+            { pubkey: token, isWritable: true, isSigner: false },
+            { pubkey: mint.publicKey, isWritable: true, isSigner: false },
+            {
+              pubkey: await getMetadata(mint.publicKey),
+              isWritable: false,
+              isSigner: false,
+            },
+            { pubkey: this.buyer.publicKey, isWritable: false, isSigner: true },
+          ],
+          instructions: [fund(this.buyer.publicKey, 10000000)],
+        });
+        assert.fail("minted with an ineligible token");
+      } catch (e) {
+        assert.equal(
+          e.msg,
+          "Token supplied for burning does not qualify for redemption"
+        );
+      }
+    });
+  });
+
+  describe("token", function () {
+    beforeEach(async function () {
+      const config = await anchor.web3.Keypair.generate();
+      this.config = config;
+      const txInstr = await createConfig(this, true, 5);
+      const linesInstr = await addConfigLines(this, 5);
+      this.tokenMint = anchor.web3.Keypair.generate();
+      this.candyMachineUuid = anchor.web3.Keypair.generate()
+        .publicKey.toBase58()
+        .slice(0, 6);
+      const [candyMachine, bump] = await getCandyMachine(
+        this.config.publicKey,
+        this.candyMachineUuid
+      );
+      this.walletToken = await getTokenWallet(
+        myWallet.publicKey,
+        this.tokenMint.publicKey
+      );
+      try {
+        const tx = await program.rpc.initializeCandyMachine(
+          bump,
+          {
+            uuid: this.candyMachineUuid,
+            price: new anchor.BN(1),
+            itemsAvailable: new anchor.BN(5),
+            goLiveDate: null,
+          },
+          noMintOptions,
+          null,
+          false,
+          {
+            accounts: {
+              candyMachine,
+              wallet: this.walletToken,
+              config: this.config.publicKey,
+              authority: this.authority.publicKey,
+              payer: myWallet.publicKey,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+            remainingAccounts: [
+              {
+                pubkey: this.tokenMint.publicKey,
+                isWritable: false,
+                isSigner: true,
+              },
+            ],
+            signers: [myWallet, this.tokenMint, this.authority, this.config],
+            instructions: [
+              anchor.web3.SystemProgram.createAccount({
+                fromPubkey: myWallet.publicKey,
+                newAccountPubkey: config.publicKey,
+                space: configArrayStart + 4 + 5 * configLineSize + 4 + 1,
+                lamports:
+                  await provider.connection.getMinimumBalanceForRentExemption(
+                    configArrayStart + 4 + 5 * configLineSize + 4 + 1
+                  ),
+                programId: programId,
+              }),
+              anchor.web3.SystemProgram.transfer({
+                fromPubkey: myWallet.publicKey,
+                toPubkey: this.authority.publicKey,
+                lamports: 5,
+              }),
+              anchor.web3.SystemProgram.createAccount({
+                fromPubkey: myWallet.publicKey,
+                newAccountPubkey: this.tokenMint.publicKey,
+                space: MintLayout.span,
+                lamports:
+                  await provider.connection.getMinimumBalanceForRentExemption(
+                    MintLayout.span
+                  ),
+                programId: TOKEN_PROGRAM_ID,
               }),
               Token.createInitMintInstruction(
                 TOKEN_PROGRAM_ID,
@@ -780,6 +4279,88 @@ describe("nft-candy-machine", function () {
       assert.ok(machine.tokenMint.equals(this.tokenMint.publicKey));
     });
 
+    it("mints into a treasury it created", async function () {
+      const [candyMachine] = await getCandyMachine(
+        this.config.publicKey,
+        this.candyMachineUuid
+      );
+      const treasury = await getTokenWallet(
+        this.authority.publicKey,
+        this.tokenMint.publicKey
+      );
+      await program.rpc.createTreasury({
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+          payer: myWallet.publicKey,
+          treasury,
+          tokenMint: this.tokenMint.publicKey,
+          associatedTokenProgram: SPL_ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
+        },
+        signers: [this.authority, myWallet],
+      });
+      const machine: CandyMachine = await program.account.candyMachine.fetch(
+        candyMachine
+      );
+      assert.ok(machine.wallet.equals(treasury));
+
+      await program.rpc.updateCandyMachine(null, new anchor.BN(0), null, {
+        accounts: {
+          candyMachine,
+          authority: this.authority.publicKey,
+        },
+        signers: [this.authority],
+      });
+      const buyer = anchor.web3.Keypair.generate();
+      const transferAuthority = anchor.web3.Keypair.generate();
+      const payingToken = await getTokenWallet(
+        buyer.publicKey,
+        this.tokenMint.publicKey
+      );
+      await mintNft(this, buyer, treasury, {
+        remainingAccounts: [
+          { pubkey: payingToken, isWritable: true, isSigner: false },
+          {
+            pubkey: transferAuthority.publicKey,
+            isWritable: false,
+            isSigner: true,
+          },
+        ],
+        signers: [transferAuthority],
+        instructions: [
+          fund(buyer.publicKey, 10000000),
+          createAssociatedTokenAccountInstruction(
+            payingToken,
+            myWallet.publicKey,
+            buyer.publicKey,
+            this.tokenMint.publicKey
+          ),
+          Token.createMintToInstruction(
+            TOKEN_PROGRAM_ID,
+            this.tokenMint.publicKey,
+            payingToken,
+            myWallet.publicKey,
+            [],
+            1
+          ),
+          Token.createApproveInstruction(
+            TOKEN_PROGRAM_ID,
+            payingToken,
+            transferAuthority.publicKey,
+            buyer.publicKey,
+            [],
+            1
+          ),
+        ],
+      });
+
+      const treasuryTokens = await connection.getTokenAccountBalance(treasury);
+      assert.equal(treasuryTokens.value.uiAmount, 1);
+    });
+
     it("mints without goLive date", async function () {
       const walletTokens = await connection.getTokenAccountBalance(
         this.walletToken
@@ -801,7 +4382,7 @@ describe("nft-candy-machine", function () {
         this.candyMachineUuid
       );
       try {
-        const tx = await program.rpc.mintNft({
+        const tx = await program.rpc.mintNft(noMintArgs, {
           accounts: {
             config: this.config.publicKey,
             candyMachine: candyMachine,
@@ -920,5 +4501,51 @@ describe("nft-candy-machine", function () {
       );
       assert.equal(payingTokenBalance.value.uiAmount, 0);
     });
+
+    it("rejects a mint into a closed wallet", async function () {
+      const tokenMint = new Token(
+        connection,
+        this.tokenMint.publicKey,
+        TOKEN_PROGRAM_ID,
+        myWallet
+      );
+      await tokenMint.closeAccount(
+        this.walletToken,
+        myWallet.publicKey,
+        myWallet,
+        []
+      );
+
+      const payingToken = await tokenMint.createAssociatedTokenAccount(
+        this.authority.publicKey
+      );
+      await tokenMint.mintTo(payingToken, myWallet, [], 1);
+      const transferAuthority = anchor.web3.Keypair.generate();
+      await tokenMint.approve(
+        payingToken,
+        transferAuthority.publicKey,
+        this.authority,
+        [],
+        1
+      );
+
+      try {
+        await mintNft(this, this.authority, this.walletToken, {
+          remainingAccounts: [
+            { pubkey: payingToken, isWritable: true, isSigner: false },
+            {
+              pubkey: transferAuthority.publicKey,
+              isWritable: false,
+              isSigner: true,
+            },
+          ],
+          signers: [transferAuthority],
+          instructions: [fund(this.authority.publicKey, 10000000)],
+        });
+        assert.fail("minted into a closed wallet");
+      } catch (e) {
+        assert.equal(e.msg, "Account is not initialized!");
+      }
+    });
   });
 });